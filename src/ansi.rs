@@ -0,0 +1,178 @@
+//! `ansi` handles the ANSI escape sequences colored program output is full of.
+//! SGR sequences (`ESC [ ... m`) become ratatui styles, every other escape
+//! sequence is dropped.
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Span,
+};
+
+/// `ESC` starts every escape sequence.
+const ESC: char = '\x1b';
+
+/// `Token` is a piece of text with escape sequences.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    /// `Text` is plain text between escape sequences.
+    Text(&'a str),
+    /// `Sgr` is the parameters of a Select Graphic Rendition sequence, like `1;31`.
+    Sgr(&'a str),
+}
+
+/// Split `text` into plain text and SGR sequences, skipping any other escape sequence.
+fn tokens(text: &str) -> impl Iterator<Item = Token<'_>> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        loop {
+            if rest.is_empty() {
+                return None;
+            }
+
+            let Some(after) = rest.strip_prefix(ESC) else {
+                let end = rest.find(ESC).unwrap_or(rest.len());
+                let (text, tail) = rest.split_at(end);
+                rest = tail;
+                return Some(Token::Text(text));
+            };
+
+            if let Some(csi) = after.strip_prefix('[') {
+                // Control Sequence: parameters and intermediates, ended by a byte in @..~
+                let end = csi
+                    .find(|c: char| ('@'..='~').contains(&c))
+                    .unwrap_or(csi.len());
+                let params = &csi[..end];
+                let is_sgr = csi[end..].starts_with('m');
+                rest = csi.get(end + 1..).unwrap_or_default();
+                if is_sgr {
+                    return Some(Token::Sgr(params));
+                }
+            } else if let Some(osc) = after.strip_prefix(']') {
+                // Operating System Command like a hyperlink, ended by BEL or ESC \
+                let (end, terminator) = match (osc.find('\x07'), osc.find("\x1b\\")) {
+                    (Some(bel), Some(st)) if st < bel => (st, 2),
+                    (Some(bel), _) => (bel, 1),
+                    (None, Some(st)) => (st, 2),
+                    (None, None) => (osc.len(), 0),
+                };
+                rest = &osc[end + terminator..];
+            } else {
+                // Any other escape is ESC followed by a single character
+                let mut chars = after.chars();
+                chars.next();
+                rest = chars.as_str();
+            }
+        }
+    })
+}
+
+/// Check if `text` contains escape sequences.
+pub fn has_escapes(text: &str) -> bool {
+    text.contains(ESC)
+}
+
+/// Remove every escape sequence from `text`.
+pub fn strip(text: &str) -> String {
+    tokens(text)
+        .filter_map(|token| match token {
+            Token::Text(text) => Some(text),
+            Token::Sgr(_) => None,
+        })
+        .collect()
+}
+
+/// Turn `text` into spans styled by its SGR sequences. The styles are patched over
+/// `base`, so text without any SGR attributes keeps the base style.
+pub fn spans(text: &str, base: Style) -> Vec<Span<'static>> {
+    let mut style = Style::default();
+    let mut spans = Vec::new();
+    for token in tokens(text) {
+        match token {
+            Token::Text(text) => spans.push(Span::styled(text.to_owned(), base.patch(style))),
+            Token::Sgr(params) => style = apply_sgr(style, params),
+        }
+    }
+    spans
+}
+
+/// Apply the SGR parameters `params` to `style`.
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    let mut codes = params
+        .split([';', ':'])
+        .map(|code| code.parse::<u16>().unwrap_or(0));
+
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => Style::default(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            5 | 6 => style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style.add_modifier(Modifier::REVERSED),
+            8 => style.add_modifier(Modifier::HIDDEN),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            21 | 22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            25 => style.remove_modifier(Modifier::SLOW_BLINK),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            28 => style.remove_modifier(Modifier::HIDDEN),
+            29 => style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg(ansi_color(code - 30)),
+            38 => match extended_color(&mut codes) {
+                Some(color) => style.fg(color),
+                None => style,
+            },
+            39 => {
+                style.fg = None;
+                style
+            }
+            40..=47 => style.bg(ansi_color(code - 40)),
+            48 => match extended_color(&mut codes) {
+                Some(color) => style.bg(color),
+                None => style,
+            },
+            49 => {
+                style.bg = None;
+                style
+            }
+            90..=97 => style.fg(ansi_color(code - 90 + 8)),
+            100..=107 => style.bg(ansi_color(code - 100 + 8)),
+            _ => style,
+        };
+    }
+    style
+}
+
+/// Read the color of a `38` or `48` code: `5;n` for the 256-color palette or
+/// `2;r;g;b` for an RGB color.
+fn extended_color(codes: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    let mut next = || codes.next().and_then(|code| u8::try_from(code).ok());
+    match next()? {
+        5 => next().map(Color::Indexed),
+        2 => Some(Color::Rgb(next()?, next()?, next()?)),
+        _ => None,
+    }
+}
+
+/// The color with the ANSI index `index`, 0 to 15.
+const fn ansi_color(index: u16) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Magenta,
+        6 => Color::Cyan,
+        7 => Color::Gray,
+        8 => Color::DarkGray,
+        9 => Color::LightRed,
+        10 => Color::LightGreen,
+        11 => Color::LightYellow,
+        12 => Color::LightBlue,
+        13 => Color::LightMagenta,
+        14 => Color::LightCyan,
+        _ => Color::White,
+    }
+}
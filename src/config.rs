@@ -0,0 +1,218 @@
+//! `config` loads the user's logz configuration file.
+
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+/// `CONFIG_FILE_NAME` is the name of the configuration file inside the logz config directory.
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// `Config` is the contents of the logz configuration file. Every section is
+/// optional and falls back to the built-in defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// `keys` is the `[keys]` section that binds keys to TUI actions.
+    pub keys: KeysConfig,
+    /// `theme` is the `[theme]` section that picks and customizes the colors.
+    pub theme: ThemeConfig,
+    /// `display` is the `[display]` section with defaults for how entries are shown.
+    pub display: DisplayConfig,
+    /// `multiline` is the `[multiline]` section that groups lines into entries.
+    pub multiline: MultilineConfig,
+    /// `formats` are the `[[formats]]` sections that define line formats.
+    pub formats: Vec<FormatConfig>,
+    /// `clipboard` is the `[clipboard]` section that sets how entries are copied.
+    pub clipboard: ClipboardConfig,
+}
+
+/// `KeysConfig` is the `[keys]` section of the configuration file.
+///
+/// ```toml
+/// [keys]
+/// preset = "emacs"
+/// scroll_down = ["j", "<Down>"]
+/// quit = ["q", "<C-x><C-c>"]
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct KeysConfig {
+    /// `preset` is the name of the built-in keymap to start from (`vim` or `emacs`).
+    pub preset: Option<String>,
+    /// `bindings` maps action names onto the keys that trigger them. An action listed
+    /// here replaces all of the preset's keys for that action.
+    #[serde(flatten)]
+    pub bindings: BTreeMap<String, Vec<String>>,
+}
+
+/// `DisplayConfig` is the `[display]` section of the configuration file.
+///
+/// ```toml
+/// [display]
+/// ansi = false
+/// error_level = "error"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DisplayConfig {
+    /// `ansi` shows the colors of escape sequences in log lines. When false, the
+    /// escape sequences are stripped and only the plain text is shown.
+    pub ansi: bool,
+    /// `error_level` is the lowest level of the entries `]e` and `[e` jump to.
+    pub error_level: String,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            ansi: true,
+            error_level: "warn".to_owned(),
+        }
+    }
+}
+
+/// `ClipboardConfig` is the `[clipboard]` section of the configuration file.
+///
+/// ```toml
+/// [clipboard]
+/// osc52 = true
+/// command = ["xclip", "-selection", "clipboard"]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClipboardConfig {
+    /// `osc52` copies by sending the text to the terminal in an OSC 52 escape
+    /// sequence, which also works over SSH and inside tmux.
+    pub osc52: bool,
+    /// `command` is a local clipboard command and its arguments, which also gets the
+    /// copied text on its standard input, for terminals without OSC 52.
+    pub command: Option<Vec<String>>,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            osc52: true,
+            command: None,
+        }
+    }
+}
+
+/// `MultilineConfig` is the `[multiline]` section of the configuration file.
+///
+/// ```toml
+/// [multiline]
+/// start = '^\d{4}-\d{2}-\d{2}'
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MultilineConfig {
+    /// `enabled` folds continuation lines into the entry before them.
+    pub enabled: bool,
+    /// `continuation` are regexes matching lines that continue the previous entry,
+    /// replacing the built-in ones.
+    pub continuation: Option<Vec<String>>,
+    /// `start` is a regex matching the first line of every entry. When set, every line
+    /// not matching it continues the previous entry and `continuation` is ignored.
+    pub start: Option<String>,
+}
+
+impl Default for MultilineConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            continuation: None,
+            start: None,
+        }
+    }
+}
+
+/// `FormatConfig` is a `[[formats]]` section of the configuration file, defining a
+/// line format by a regex whose named groups become fields.
+///
+/// ```toml
+/// [[formats]]
+/// name = "myapp"
+/// pattern = '^(?P<ts>\S+ \S+) \[(?P<level>\w+)\] (?P<msg>.*)$'
+/// timestamp = "%Y-%m-%d %H:%M:%S%.f"
+/// files = "myapp*.log"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatConfig {
+    /// `name` is how the format is shown and referred to.
+    pub name: String,
+    /// `pattern` is a regex matching a line, with a named group for every field.
+    pub pattern: String,
+    /// `timestamp` is the strftime-style format of the time field, which is read in
+    /// the common formats when unset.
+    pub timestamp: Option<String>,
+    /// `files` is a glob of the file names the format is used for. Formats without
+    /// it are detected like the built-in ones.
+    pub files: Option<String>,
+}
+
+/// `ThemeConfig` is the `[theme]` section of the configuration file.
+///
+/// ```toml
+/// [theme]
+/// name = "light"
+/// colors = "256"
+/// header = { fg = "#005f87", bold = true }
+/// selected = { bg = "lightyellow" }
+///
+/// [theme.levels]
+/// warn = { fg = "magenta" }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// `name` is the built-in theme to start from (`dark`, `light` or `high-contrast`).
+    pub name: Option<String>,
+    /// `colors` is the color support of the terminal (`auto`, `none`, `16`, `256` or
+    /// `truecolor`), detected from the environment when unset.
+    pub colors: Option<String>,
+    /// `levels` maps log level names onto the style of entries with that level.
+    pub levels: BTreeMap<String, StyleConfig>,
+    /// `elements` maps UI element names onto their style, replacing the built-in one.
+    #[serde(flatten)]
+    pub elements: BTreeMap<String, StyleConfig>,
+}
+
+/// `StyleConfig` is the style of a single UI element or log level in the `[theme]` section.
+/// Colors are names like `red` or `light-blue`, `#rrggbb` or a 256-color index.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StyleConfig {
+    /// `fg` is the text color.
+    pub fg: Option<String>,
+    /// `bg` is the background color.
+    pub bg: Option<String>,
+    /// `bold` makes the text bold.
+    pub bold: Option<bool>,
+    /// `dim` makes the text faint.
+    pub dim: Option<bool>,
+    /// `italic` makes the text italic.
+    pub italic: Option<bool>,
+    /// `underline` underlines the text.
+    pub underline: Option<bool>,
+    /// `reversed` swaps the text and background colors.
+    pub reversed: Option<bool>,
+}
+
+impl Config {
+    /// `path` is the location of the configuration file, usually
+    /// `~/.config/logz/config.toml`.
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("logz").join(CONFIG_FILE_NAME))
+    }
+
+    /// Load the configuration file, using the defaults when it does not exist.
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = Self::path().filter(|path| path.is_file()) else {
+            return Ok(Self::default());
+        };
+
+        let contents = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        toml::from_str(&contents).map_err(|e| format!("{}: {e}", path.display()))
+    }
+}
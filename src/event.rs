@@ -0,0 +1,186 @@
+//! `event` contains the unified event channel that drives the `Tui` main loop.
+
+use crossterm::event::{self, Event, KeyEvent, MouseEvent};
+use std::{
+    process::ExitStatus,
+    sync::{
+        Arc, Condvar, Mutex, MutexGuard, PoisonError,
+        mpsc::{Receiver, Sender, channel},
+    },
+    thread,
+    time::Duration,
+};
+
+/// `AppEvent` is any event the main loop reacts to.
+#[derive(Debug, Clone)]
+pub enum AppEvent {
+    /// `Key` is a key event read from the terminal.
+    Key(KeyEvent),
+    /// `Mouse` is a mouse event read from the terminal.
+    Mouse(MouseEvent),
+    /// `Resize` is sent when the terminal changes size.
+    Resize,
+    /// `FileChanged` is sent by the file watcher when the log file was modified.
+    FileChanged,
+    /// `Tick` is sent periodically by the timer thread.
+    Tick,
+    /// `Interrupt` is sent on Ctrl-C while the terminal isn't in raw mode, like when
+    /// following a file without the TUI.
+    Interrupt,
+    /// `PipeDone` is sent when a command the entries were piped to exited, with what it
+    /// wrote to its standard output and error and how it exited.
+    PipeDone(Result<(String, ExitStatus), String>),
+}
+
+impl AppEvent {
+    /// Check if the event was read from the terminal by the input thread.
+    pub const fn is_input(&self) -> bool {
+        matches!(self, Self::Key(_) | Self::Mouse(_) | Self::Resize)
+    }
+}
+
+/// `InputGate` is what the input thread waits for before reading the terminal.
+#[derive(Debug, Default)]
+struct InputGate {
+    /// `unhandled` is the number of events the input thread sent that the main loop
+    /// hasn't handled yet. Nothing is read until they are, since handling one may hand
+    /// the terminal to another program.
+    unhandled: usize,
+    /// `paused` stops the input thread from reading while another program uses the
+    /// terminal.
+    paused: bool,
+    /// `reading` is set while the input thread waits for terminal input.
+    reading: bool,
+}
+
+/// `SharedGate` is the `InputGate` with the condition variable signalling its changes.
+type SharedGate = Arc<(Mutex<InputGate>, Condvar)>;
+
+/// Lock the `InputGate` of `gate`. The gate stays usable after a thread panicked
+/// holding it, since its fields are always consistent.
+fn lock(gate: &(Mutex<InputGate>, Condvar)) -> MutexGuard<'_, InputGate> {
+    gate.0.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// `EventLoop` merges terminal input, file change notifications and timer
+/// ticks into a single channel.
+#[derive(Debug)]
+pub struct EventLoop {
+    /// `sender` is cloned out to producers like the file watcher.
+    sender: Sender<AppEvent>,
+    /// `receiver` is where the main loop waits for the next event.
+    receiver: Receiver<AppEvent>,
+    /// `input_gate` tells the input thread when it may read the terminal.
+    input_gate: SharedGate,
+}
+
+impl EventLoop {
+    /// Create a new `EventLoop` and spawn the terminal input and timer threads.
+    pub fn new(tick_rate: Duration) -> Self {
+        let (sender, receiver) = channel();
+
+        let input_sender = sender.clone();
+        let input_gate = SharedGate::default();
+        let gate = Arc::clone(&input_gate);
+        thread::spawn(move || Self::read_terminal_events(&input_sender, &gate));
+
+        let tick_sender = sender.clone();
+        thread::spawn(move || {
+            loop {
+                thread::sleep(tick_rate);
+                if tick_sender.send(AppEvent::Tick).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            sender,
+            receiver,
+            input_gate,
+        }
+    }
+
+    /// Forward crossterm events to the channel until the receiver goes away. Each read
+    /// blocks until there is input, and only starts once the last event was handled
+    /// and reading isn't paused.
+    fn read_terminal_events(sender: &Sender<AppEvent>, gate: &(Mutex<InputGate>, Condvar)) {
+        loop {
+            let mut input = gate
+                .1
+                .wait_while(lock(gate), |input| input.paused || input.unhandled > 0)
+                .unwrap_or_else(PoisonError::into_inner);
+            input.reading = true;
+            drop(input);
+
+            let app_event = match event::read() {
+                Ok(Event::Key(key)) => Some(AppEvent::Key(key)),
+                Ok(Event::Mouse(mouse)) => Some(AppEvent::Mouse(mouse)),
+                Ok(Event::Resize(_, _)) => Some(AppEvent::Resize),
+                Ok(_) => None,
+                Err(_) => break,
+            };
+
+            let mut input = lock(gate);
+            input.reading = false;
+            input.unhandled += usize::from(app_event.is_some());
+            gate.1.notify_all();
+            drop(input);
+
+            if let Some(app_event) = app_event
+                && sender.send(app_event).is_err()
+            {
+                break;
+            }
+        }
+        lock(gate).reading = false;
+        gate.1.notify_all();
+    }
+
+    /// Tell the input thread that `count` events it sent were handled, so it can read
+    /// the next ones.
+    pub fn input_handled(&self, count: usize) {
+        if count == 0 {
+            return;
+        }
+        let mut input = lock(&self.input_gate);
+        input.unhandled = input.unhandled.saturating_sub(count);
+        self.input_gate.1.notify_all();
+    }
+
+    /// Stop reading the terminal, so that a program run in the foreground gets all of
+    /// the input. Returns once the input thread isn't reading, which is right away
+    /// while an event it sent is being handled, since it waits for that first.
+    pub fn pause_input(&self) {
+        let mut input = lock(&self.input_gate);
+        input.paused = true;
+        let _input = self
+            .input_gate
+            .1
+            .wait_while(input, |input| input.reading)
+            .unwrap_or_else(PoisonError::into_inner);
+    }
+
+    /// Read the terminal again after `pause_input`.
+    pub fn resume_input(&self) {
+        lock(&self.input_gate).paused = false;
+        self.input_gate.1.notify_all();
+    }
+
+    /// Get a new sender that can push events into the loop.
+    pub fn sender(&self) -> Sender<AppEvent> {
+        self.sender.clone()
+    }
+
+    /// Block until the next event arrives.
+    pub fn next(&self) -> std::io::Result<AppEvent> {
+        self.receiver
+            .recv()
+            .map_err(|e| std::io::Error::other(format!("Event channel closed: {e}")))
+    }
+
+    /// Take every event that is already waiting without blocking.
+    pub fn drain(&self) -> impl Iterator<Item = AppEvent> + '_ {
+        self.receiver.try_iter()
+    }
+}
@@ -0,0 +1,205 @@
+//! `export` writes log entries to a file as plain text, JSON Lines, CSV or an HTML
+//! page, for `:export` in the TUI and `logz export`.
+
+use crate::log_entry::{LogEntry, LogLevel};
+use serde_json::{Map, Value};
+use std::{io, path::Path};
+
+/// `LINE_COLUMN` is the CSV column holding the line number of an entry.
+pub const LINE_COLUMN: &str = "line";
+
+/// `CONTENT_COLUMN` is the CSV column holding the whole content of an entry.
+pub const CONTENT_COLUMN: &str = "content";
+
+/// `HTML_STYLE` is the style sheet of exported HTML pages, with the level colors of
+/// the dark theme.
+const HTML_STYLE: &str = "\
+body { background: #1e1e1e; color: #e8e8e8; font-family: sans-serif; margin: 2em; }
+h1 { font-size: 1.2em; margin: 0; }
+p { color: #909090; margin: 0.4em 0 1.2em; }
+table { border-collapse: collapse; }
+td { vertical-align: top; padding: 0 0.6em; }
+td.line { color: #707070; text-align: right; user-select: none; }
+pre { font-family: monospace; margin: 0; white-space: pre-wrap; }
+tr.trace { color: #808080; }
+tr.debug { color: #b0b0b0; }
+tr.info { color: #e8e8e8; }
+tr.warn { color: #e5c07b; }
+tr.error { color: #ff6b6b; }
+tr.fatal { color: #ff3030; font-weight: bold; }
+";
+
+/// `ExportFormat` is the kind of file entries are exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// `Plain` writes the content of each entry as it is in the log file, without its
+    /// color escape sequences.
+    Plain,
+    /// `JsonLines` writes each entry as a JSON object with its line, time, level,
+    /// parsed fields and content.
+    JsonLines,
+    /// `Csv` writes a row for each entry with a column for each chosen field.
+    Csv,
+    /// `Html` writes a self-contained page showing the entries in their level colors.
+    Html,
+}
+
+impl ExportFormat {
+    /// `NAMES` are the names formats are chosen by, for completion.
+    pub const NAMES: [&str; 4] = ["text", "jsonl", "csv", "html"];
+
+    /// Parse a format name like `jsonl` or `csv`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "text" | "txt" | "plain" | "log" => Some(Self::Plain),
+            "jsonl" | "json" | "ndjson" => Some(Self::JsonLines),
+            "csv" => Some(Self::Csv),
+            "html" | "htm" => Some(Self::Html),
+            _ => None,
+        }
+    }
+
+    /// The format of the file at `path`, from its extension. Files with other
+    /// extensions are written as plain text.
+    pub fn from_path(path: &str) -> Self {
+        Path::new(path)
+            .extension()
+            .and_then(|extension| Self::parse(&extension.to_string_lossy()))
+            .unwrap_or(Self::Plain)
+    }
+}
+
+/// The CSV columns for the `columns` of the table view: the line number, the
+/// `columns` and, when none of the `entries` have fields, their content.
+pub fn csv_columns(columns: &[String], entries: &[&LogEntry]) -> Vec<String> {
+    let mut names = vec![LINE_COLUMN.to_owned()];
+    names.extend(columns.iter().cloned());
+    if entries.iter().all(|entry| entry.fields.is_empty()) {
+        names.push(CONTENT_COLUMN.to_owned());
+    }
+    names
+}
+
+/// Write `entries` to `out` in `format`. `columns` are the columns of CSV files, and
+/// `title` heads HTML pages.
+pub fn write(
+    out: &mut impl io::Write,
+    format: ExportFormat,
+    entries: &[&LogEntry],
+    columns: &[String],
+    title: &str,
+) -> io::Result<()> {
+    match format {
+        ExportFormat::Plain => {
+            for entry in entries {
+                writeln!(out, "{}", entry.content)?;
+            }
+        }
+        ExportFormat::JsonLines => {
+            for entry in entries {
+                writeln!(out, "{}", json(entry))?;
+            }
+        }
+        ExportFormat::Csv => {
+            let header: Vec<String> = columns.iter().map(|name| csv_quote(name)).collect();
+            write!(out, "{}\r\n", header.join(","))?;
+            for entry in entries {
+                let row: Vec<String> = columns
+                    .iter()
+                    .map(|name| csv_quote(&column_value(entry, name)))
+                    .collect();
+                write!(out, "{}\r\n", row.join(","))?;
+            }
+        }
+        ExportFormat::Html => write_html(out, entries, title)?,
+    }
+    out.flush()
+}
+
+/// The lowercase name of `level`, as levels are written in exported files.
+fn level_name(level: LogLevel) -> String {
+    level.to_string().to_lowercase()
+}
+
+/// `entry` as a JSON object with its line number counting from 1, its time in
+/// RFC 3339, its level, its parsed fields and its content.
+pub fn json(entry: &LogEntry) -> Value {
+    let mut object = Map::new();
+    object.insert("line".into(), Value::from(entry.line + 1));
+    if let Some(time) = entry.timestamp {
+        object.insert("time".into(), Value::from(time.to_rfc3339()));
+    }
+    if let Some(level) = entry.level {
+        object.insert("level".into(), Value::from(level_name(level)));
+    }
+    if !entry.fields.is_empty() {
+        let fields = entry
+            .fields
+            .iter()
+            .map(|field| (field.name.clone(), Value::from(field.value.clone())))
+            .collect();
+        object.insert("fields".into(), Value::Object(fields));
+    }
+    object.insert("content".into(), Value::from(entry.content.clone()));
+    Value::Object(object)
+}
+
+/// The value of the CSV column `name` for `entry`. The `level` column falls back to
+/// the detected level of entries without a level field.
+fn column_value(entry: &LogEntry, name: &str) -> String {
+    match (name, entry.field(name)) {
+        (_, Some(value)) => value.to_owned(),
+        (LINE_COLUMN, None) => (entry.line + 1).to_string(),
+        (CONTENT_COLUMN, None) => entry.content.clone(),
+        ("level", None) => entry.level.map(level_name).unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+/// Quote `value` for a CSV file when it has a comma, quote or line break, doubling
+/// the quotes in it.
+fn csv_quote(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Escape the characters of `text` that mean something in HTML.
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Write `entries` to `out` as an HTML page titled `title`, with a row per entry
+/// colored by its level.
+fn write_html(out: &mut impl io::Write, entries: &[&LogEntry], title: &str) -> io::Result<()> {
+    let title = html_escape(title);
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{title}</title>")?;
+    writeln!(out, "<style>\n{HTML_STYLE}</style>\n</head>\n<body>")?;
+    writeln!(out, "<h1>{title}</h1>")?;
+    let noun = if entries.len() == 1 {
+        "entry"
+    } else {
+        "entries"
+    };
+    writeln!(out, "<p>{} {noun}</p>\n<table>", entries.len())?;
+    for entry in entries {
+        let class = entry
+            .level
+            .map(|level| format!(" class=\"{}\"", level_name(level)))
+            .unwrap_or_default();
+        writeln!(
+            out,
+            "<tr{class}><td class=\"line\">{}</td><td><pre>{}</pre></td></tr>",
+            entry.line + 1,
+            html_escape(&entry.content)
+        )?;
+    }
+    writeln!(out, "</table>\n</body>\n</html>")
+}
@@ -0,0 +1,648 @@
+//! `filter` decides which `LogEntry` rows are shown.
+
+use crate::log_entry::{LogEntry, LogLevel};
+use regex::Regex;
+use std::{cmp::Ordering, fmt};
+
+/// `COMPARISONS` are the comparison operators of a filter, with the orderings they
+/// accept. Longer operators come first so that `>=` isn't read as `>`.
+const COMPARISONS: [(&str, &[Ordering]); 6] = [
+    (">=", &[Ordering::Greater, Ordering::Equal]),
+    ("<=", &[Ordering::Less, Ordering::Equal]),
+    ("!=", &[Ordering::Less, Ordering::Greater]),
+    ("=", &[Ordering::Equal]),
+    (">", &[Ordering::Greater]),
+    ("<", &[Ordering::Less]),
+];
+
+/// `MATCHES` are the regex operators of a filter, with whether they are negated.
+const MATCHES: [(&str, bool); 2] = [("!~", true), ("~", false)];
+
+/// `LINE_FIELD` is the name comparing the line number an entry starts at, counting
+/// from 1, rather than a field.
+const LINE_FIELD: &str = "@line";
+
+/// `KEYWORDS` start or join the terms of a query rather than being text to look for.
+const KEYWORDS: [&str; 6] = ["and", "or", "not", "&&", "||", "!"];
+
+/// `Filter` is a condition a `LogEntry` has to meet to be shown.
+#[derive(Debug, Clone)]
+pub enum Filter {
+    /// `Level` keeps entries whose level compares to the given level, e.g. `level>=warn`.
+    Level(Vec<Ordering>, LogLevel),
+    /// `Line` keeps entries whose first line number, counting from 1, compares to the
+    /// given one, e.g. `@line>=120`.
+    Line(Vec<Ordering>, usize),
+    /// `Field` keeps entries with a field comparing to the value, e.g. `status>=500`.
+    /// Values that are both numbers compare as numbers.
+    Field(String, Vec<Ordering>, String),
+    /// `Match` keeps entries with a field matching the regex, e.g. `path~/api/`, or
+    /// not matching it when negated, e.g. `path!~/health`.
+    Match(String, Regex, bool),
+    /// `Text` keeps entries containing the text, ignoring case unless it has uppercase letters.
+    Text(String),
+    /// `Not` keeps entries not passing the filter, e.g. `not msg~healthcheck`.
+    Not(Box<Self>),
+    /// `And` keeps entries passing all of the filters, e.g. `level>=warn and app=api`.
+    And(Vec<Self>),
+    /// `Or` keeps entries passing any of the filters, e.g. `status>=500 or level=error`.
+    Or(Vec<Self>),
+}
+
+impl Filter {
+    /// Parse a query of conditions like `level>=warn`, `status>=500`, `user.id=42`,
+    /// `path~/api/`, `path!~^/health` or plain text, joined by `and`, `or` and `not` and
+    /// grouped with parentheses. Conditions next to each other must all be met, and
+    /// text or values in double quotes are taken as they are.
+    pub fn parse(text: &str) -> Result<Self, QueryError> {
+        let mut parser = Parser { text, position: 0 };
+        parser.skip_whitespace();
+        if parser.rest().is_empty() {
+            return Err(parser.error(0, "empty query"));
+        }
+        let filter = parser.or()?;
+        parser.skip_whitespace();
+        if !parser.rest().is_empty() {
+            return Err(parser.error(parser.position, "unexpected ')'"));
+        }
+        Ok(filter)
+    }
+
+    /// A filter keeping the entries starting from line `first` to line `last`,
+    /// counting from 1.
+    pub fn lines(first: usize, last: usize) -> Self {
+        Self::And(vec![
+            Self::Line(vec![Ordering::Greater, Ordering::Equal], first),
+            Self::Line(vec![Ordering::Less, Ordering::Equal], last),
+        ])
+    }
+
+    /// Check if the entry passes the filter. Entries without a level never pass a level
+    /// filter, and entries without the field never pass a field filter.
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        match self {
+            Self::Level(orderings, level) => entry
+                .level
+                .is_some_and(|entry_level| orderings.contains(&entry_level.cmp(level))),
+            Self::Line(orderings, line) => orderings.contains(&(entry.line + 1).cmp(line)),
+            Self::Field(name, orderings, value) => entry
+                .field(name)
+                .is_some_and(|field| orderings.contains(&compare_values(field, value))),
+            Self::Match(name, regex, negated) => entry
+                .field(name)
+                .is_some_and(|field| regex.is_match(field) != *negated),
+            Self::Text(text) => contains_smart_case(&entry.content, text),
+            Self::Not(filter) => !filter.matches(entry),
+            Self::And(filters) => filters.iter().all(|filter| filter.matches(entry)),
+            Self::Or(filters) => filters.iter().any(|filter| filter.matches(entry)),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Level(orderings, level) => write!(
+                f,
+                "level{}{}",
+                comparison(orderings),
+                level.to_string().to_lowercase()
+            ),
+            Self::Line(orderings, line) => {
+                write!(f, "{LINE_FIELD}{}{line}", comparison(orderings))
+            }
+            Self::Field(name, orderings, value) => {
+                write!(f, "{name}{}{}", comparison(orderings), quote(value))
+            }
+            Self::Match(name, regex, negated) => {
+                let op = if *negated { "!~" } else { "~" };
+                write!(f, "{name}{op}{}", quote(regex.as_str()))
+            }
+            // Text that would read back as something else is quoted
+            Self::Text(text) => match Self::parse(text) {
+                Ok(Self::Text(parsed)) if parsed == *text => write!(f, "{text}"),
+                _ => write!(f, "\"{}\"", escape(text)),
+            },
+            Self::Not(filter) => match filter.as_ref() {
+                Self::And(_) | Self::Or(_) => write!(f, "not ({filter})"),
+                _ => write!(f, "not {filter}"),
+            },
+            Self::And(filters) => {
+                for (i, filter) in filters.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " and ")?;
+                    }
+                    match filter {
+                        Self::Or(_) => write!(f, "({filter})")?,
+                        // Plain words around `and` would read back as one text
+                        Self::Text(text) => write!(f, "\"{}\"", escape(text))?,
+                        _ => write!(f, "{filter}")?,
+                    }
+                }
+                Ok(())
+            }
+            Self::Or(filters) => {
+                for (i, filter) in filters.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " or ")?;
+                    }
+                    match filter {
+                        Self::Text(text) => write!(f, "\"{}\"", escape(text))?,
+                        _ => write!(f, "{filter}")?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// `QueryError` is why a query could not be parsed, with where it went wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryError {
+    /// `message` describes what is wrong.
+    pub message: String,
+    /// `position` is the byte offset in the query where it went wrong.
+    pub position: usize,
+    /// `column` is the character in the query where it went wrong, counting from 1.
+    pub column: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column)
+    }
+}
+
+/// `Parser` reads a query from left to right.
+struct Parser<'a> {
+    /// `text` is the whole query.
+    text: &'a str,
+    /// `position` is the byte offset of the next character to read.
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// The part of the query that is left to read.
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    /// The next character to read.
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Skip the whitespace before the next term.
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// A `QueryError` with `message` at the byte offset `position`.
+    fn error(&self, position: usize, message: impl Into<String>) -> QueryError {
+        QueryError {
+            message: message.into(),
+            position,
+            column: self.text[..position].chars().count() + 1,
+        }
+    }
+
+    /// Read one of the `keywords` if it is next, ignoring case.
+    fn keyword(&mut self, keywords: &[&str]) -> bool {
+        self.skip_whitespace();
+        let rest = self.rest();
+        for keyword in keywords {
+            let Some(prefix) = rest.get(..keyword.len()) else {
+                continue;
+            };
+            if !prefix.eq_ignore_ascii_case(keyword) {
+                continue;
+            }
+            let after = rest[keyword.len()..].chars().next();
+            let complete = if keyword.starts_with(char::is_alphabetic) {
+                after.is_none_or(|c| c.is_whitespace() || matches!(c, '(' | '"'))
+            } else {
+                // `!=` and `!~` are operators rather than `not`
+                !matches!(after, Some('=' | '~'))
+            };
+            if complete {
+                self.position += keyword.len();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Check if a keyword is next, without reading it.
+    fn at_keyword(&mut self) -> bool {
+        let position = self.position;
+        let found = self.keyword(&KEYWORDS);
+        self.position = position;
+        found
+    }
+
+    /// Check if a word like `not` is next, followed by plain text rather than another
+    /// keyword, a condition or a parenthesis, without reading it.
+    fn word_ahead(&mut self) -> bool {
+        let position = self.position;
+        let found = self.keyword(&["and", "or", "not"]) && {
+            self.skip_whitespace();
+            !matches!(self.peek(), None | Some('(' | ')' | '"'))
+                && !self.at_keyword()
+                && !self.comparison_ahead()
+        };
+        self.position = position;
+        found
+    }
+
+    /// Read terms joined by `or`.
+    fn or(&mut self) -> Result<Filter, QueryError> {
+        let mut filters = vec![self.and()?];
+        while self.keyword(&["or", "||"]) {
+            filters.push(self.and()?);
+        }
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            Filter::Or(filters)
+        })
+    }
+
+    /// Read terms joined by `and`, or just next to each other.
+    fn and(&mut self) -> Result<Filter, QueryError> {
+        let mut filters = vec![self.unary()?];
+        loop {
+            if !self.keyword(&["and", "&&"]) {
+                let position = self.position;
+                let end = self.rest().is_empty() || self.rest().starts_with(')');
+                if end || self.keyword(&["or", "||"]) {
+                    self.position = position;
+                    break;
+                }
+            }
+            filters.push(self.unary()?);
+        }
+        Ok(if filters.len() == 1 {
+            filters.remove(0)
+        } else {
+            Filter::And(filters)
+        })
+    }
+
+    /// Read a condition, a negated term or a term in parentheses.
+    fn unary(&mut self) -> Result<Filter, QueryError> {
+        if self.keyword(&["not", "!"]) {
+            return Ok(Filter::Not(Box::new(self.unary()?)));
+        }
+        self.skip_whitespace();
+        if self.peek() == Some('(') {
+            self.position += 1;
+            let filter = self.or()?;
+            self.skip_whitespace();
+            if self.peek() != Some(')') {
+                return Err(self.error(self.position, "expected ')'"));
+            }
+            self.position += 1;
+            return Ok(filter);
+        }
+        self.condition()
+    }
+
+    /// Read a comparison, quoted text, or plain text up to the next keyword, condition
+    /// or parenthesis. Like before queries could be combined, `and`, `or` and `not`
+    /// between words are part of the text, so `connection not found` is one text.
+    fn condition(&mut self) -> Result<Filter, QueryError> {
+        self.skip_whitespace();
+        let start = self.position;
+        match self.peek() {
+            None => return Err(self.error(start, "expected a condition")),
+            Some(')') => return Err(self.error(start, "unexpected ')'")),
+            Some('"') => return self.quoted().map(Filter::Text),
+            Some(_) => {}
+        }
+        if let Some((name, op)) = self.comparison() {
+            return self.compare(name, op);
+        }
+
+        self.raw();
+        let mut end = self.position;
+        loop {
+            self.skip_whitespace();
+            let next = matches!(self.peek(), None | Some('(' | ')' | '"'));
+            let joins = self.at_keyword() && !self.word_ahead();
+            if next || joins || self.comparison_ahead() {
+                self.position = end;
+                break;
+            }
+            self.raw();
+            end = self.position;
+        }
+        Ok(Filter::Text(self.text[start..end].to_owned()))
+    }
+
+    /// Read a field name and the operator after it, if they are next.
+    fn comparison(&mut self) -> Option<(&'a str, &'static str)> {
+        let start = self.position;
+        let rest = self.rest();
+        let name_end = rest
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '.' | '@' | '-')))
+            .unwrap_or(rest.len());
+        let name = &rest[..name_end];
+        self.position += name_end;
+        self.skip_whitespace();
+
+        let op = MATCHES
+            .into_iter()
+            .map(|(op, _)| op)
+            .chain(["=="])
+            .chain(COMPARISONS.into_iter().map(|(op, _)| op))
+            .find(|op| self.rest().starts_with(op));
+        match op {
+            Some(op) if !name.is_empty() => {
+                self.position += op.len();
+                Some((name, op))
+            }
+            _ => {
+                self.position = start;
+                None
+            }
+        }
+    }
+
+    /// Check if a comparison is next, without reading it.
+    fn comparison_ahead(&mut self) -> bool {
+        let position = self.position;
+        let found = self.comparison().is_some();
+        self.position = position;
+        found
+    }
+
+    /// Read the value compared to the field `name` by `op`, and make the condition.
+    fn compare(&mut self, name: &str, op: &str) -> Result<Filter, QueryError> {
+        self.skip_whitespace();
+        let start = self.position;
+        let value = if self.peek() == Some('"') {
+            self.quoted()?
+        } else {
+            let value = self.raw();
+            if value.is_empty() {
+                return Err(self.error(start, format!("expected a value after '{op}'")));
+            }
+            value.to_owned()
+        };
+
+        if let Some((_, negated)) = MATCHES.into_iter().find(|(match_op, _)| *match_op == op) {
+            let regex = Regex::new(&value).map_err(|e| {
+                // Only keep the line saying what is wrong from the multi-line message
+                let reason = e.to_string();
+                let reason = reason
+                    .lines()
+                    .find_map(|line| line.strip_prefix("error: "))
+                    .unwrap_or(&reason)
+                    .to_owned();
+                self.error(start, format!("invalid pattern '{value}': {reason}"))
+            })?;
+            return Ok(Filter::Match(name.to_owned(), regex, negated));
+        }
+
+        let orderings = COMPARISONS
+            .into_iter()
+            .find(|(comparison, _)| *comparison == op)
+            .map_or(&[Ordering::Equal][..], |(_, orderings)| orderings)
+            .to_vec();
+        if name == "level" {
+            let level = LogLevel::parse(&value)
+                .ok_or_else(|| self.error(start, format!("unknown level '{value}'")))?;
+            return Ok(Filter::Level(orderings, level));
+        }
+        if name == LINE_FIELD {
+            let line = value
+                .parse()
+                .map_err(|_| self.error(start, format!("'{value}' is not a line number")))?;
+            return Ok(Filter::Line(orderings, line));
+        }
+        Ok(Filter::Field(name.to_owned(), orderings, value))
+    }
+
+    /// Read text in double quotes, in which `\"` is a quote and `\\` a backslash.
+    fn quoted(&mut self) -> Result<String, QueryError> {
+        let start = self.position;
+        let mut text = String::new();
+        let mut chars = self.text[start + 1..].char_indices();
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.position = start + 1 + offset + 1;
+                    return Ok(text);
+                }
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => text.push(escaped),
+                    None => break,
+                },
+                c => text.push(c),
+            }
+        }
+        Err(self.error(start, "unclosed '\"'"))
+    }
+
+    /// Read a word up to whitespace or a `)` closing a group. Parentheses inside the
+    /// word, like in `path~^/(v1|v2)/`, are part of it.
+    fn raw(&mut self) -> &'a str {
+        let rest = self.rest();
+        let mut depth = 0_usize;
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| match c {
+                '(' => {
+                    depth += 1;
+                    false
+                }
+                ')' if depth == 0 => true,
+                ')' => {
+                    depth -= 1;
+                    false
+                }
+                c => c.is_whitespace(),
+            })
+            .map_or(rest.len(), |(i, _)| i);
+        self.position += end;
+        &rest[..end]
+    }
+}
+
+/// `value` as it is written in a query, quoted when it has to be.
+fn quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '(' | ')'));
+    if plain {
+        value.to_owned()
+    } else {
+        format!("\"{}\"", escape(value))
+    }
+}
+
+/// `text` with its backslashes and quotes escaped for a quoted value.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// The operator of a comparison accepting `orderings`.
+fn comparison(orderings: &[Ordering]) -> &'static str {
+    COMPARISONS
+        .into_iter()
+        .find(|(_, accepted)| *accepted == orderings)
+        .map_or("=", |(op, _)| op)
+}
+
+/// Compare two field values, as numbers when both are numbers.
+pub fn compare_values(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// Check if `haystack` contains `needle`, ignoring case unless `needle` has uppercase letters.
+pub fn contains_smart_case(haystack: &str, needle: &str) -> bool {
+    if needle.chars().any(char::is_uppercase) {
+        haystack.contains(needle)
+    } else {
+        haystack.to_lowercase().contains(needle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::LogFormat;
+
+    /// The query `text` parsed and written back.
+    fn display(text: &str) -> String {
+        Filter::parse(text)
+            .unwrap_or_else(|e| panic!("{text}: {e}"))
+            .to_string()
+    }
+
+    /// The error parsing the query `text`, with its column.
+    fn error(text: &str) -> (String, usize) {
+        let e = Filter::parse(text).expect_err(text);
+        (e.message, e.column)
+    }
+
+    /// Check if the query `text` keeps the logfmt `line`.
+    fn keeps(text: &str, line: &str) -> bool {
+        let entry = LogFormat::Logfmt.entry(0, line.to_owned());
+        Filter::parse(text)
+            .unwrap_or_else(|e| panic!("{text}: {e}"))
+            .matches(&entry)
+    }
+
+    #[test]
+    fn display_reads_back_as_the_same_query() {
+        for (query, written) in [
+            ("level>=warn", "level>=warn"),
+            ("level = Error", "level=error"),
+            ("status >= 500 && path~/api/", "status>=500 and path~/api/"),
+            ("status==200", "status=200"),
+            ("msg=\"hello world\"", "msg=\"hello world\""),
+            ("path~^/(v1|v2)/", "path~\"^/(v1|v2)/\""),
+            ("@line>=3 and @line<=9", "@line>=3 and @line<=9"),
+            ("x=1 or y=2 and z=3", "x=1 or y=2 and z=3"),
+            ("(x=1 or y=2) and z=3", "(x=1 or y=2) and z=3"),
+            ("not (x=1 or y=2)", "not (x=1 or y=2)"),
+            ("\"error\" or \"warning\"", "\"error\" or \"warning\""),
+            ("\"a and b\"", "a and b"),
+            ("\"level>=warn\"", "\"level>=warn\""),
+            ("\"say \\\"hi\\\"\"", "\"say \\\"hi\\\"\""),
+        ] {
+            assert_eq!(display(query), written, "{query}");
+            assert_eq!(display(written), written, "{written}");
+        }
+    }
+
+    #[test]
+    fn keywords_between_plain_words_are_text() {
+        assert_eq!(display("connection not found"), "connection not found");
+        assert!(keeps(
+            "connection not found",
+            "msg=\"a connection not found here\" x=1"
+        ));
+        assert!(!keeps(
+            "connection not found",
+            "msg=\"connection found\" x=1"
+        ));
+
+        assert!(matches!(Filter::parse("not found"), Ok(Filter::Not(_))));
+        assert!(matches!(
+            Filter::parse("error or warning"),
+            Ok(Filter::Text(_))
+        ));
+        assert!(matches!(
+            Filter::parse("\"error\" or warning"),
+            Ok(Filter::Or(_))
+        ));
+        assert!(matches!(
+            Filter::parse("connection and not found"),
+            Ok(Filter::And(_))
+        ));
+        assert!(matches!(
+            Filter::parse("level>=warn and timeout"),
+            Ok(Filter::And(_))
+        ));
+    }
+
+    #[test]
+    fn bang_is_not_unless_it_starts_an_operator() {
+        assert!(matches!(
+            Filter::parse("status!=200"),
+            Ok(Filter::Field(_, ref orderings, _)) if orderings == &[Ordering::Less, Ordering::Greater]
+        ));
+        assert!(matches!(
+            Filter::parse("path!~^/health"),
+            Ok(Filter::Match(_, _, true))
+        ));
+        assert!(matches!(Filter::parse("!found"), Ok(Filter::Not(_))));
+        assert_eq!(display("!status=200"), "not status=200");
+
+        assert!(keeps("status!=200", "status=500 path=/api"));
+        assert!(!keeps("status!=200", "status=200 path=/api"));
+        assert!(keeps("path!~^/health", "status=200 path=/api"));
+        assert!(!keeps("path!~^/health", "status=200 path=/healthz"));
+    }
+
+    #[test]
+    fn errors_point_at_where_the_query_goes_wrong() {
+        assert_eq!(error("  "), ("empty query".to_owned(), 1));
+        assert_eq!(error("(level>=warn"), ("expected ')'".to_owned(), 13));
+        assert_eq!(error("a )"), ("unexpected ')'".to_owned(), 3));
+        assert_eq!(error("a and"), ("expected a condition".to_owned(), 6));
+        assert_eq!(
+            error("status>="),
+            ("expected a value after '>='".to_owned(), 9)
+        );
+        assert_eq!(error("level=loud"), ("unknown level 'loud'".to_owned(), 7));
+        assert_eq!(error("@line<x"), ("'x' is not a line number".to_owned(), 7));
+        assert_eq!(error("msg=\"open"), ("unclosed '\"'".to_owned(), 5));
+        assert_eq!(error("é and \"x"), ("unclosed '\"'".to_owned(), 7));
+
+        let (message, column) = error("path~(");
+        assert!(message.starts_with("invalid pattern '('"), "{message}");
+        assert_eq!(column, 6);
+    }
+
+    #[test]
+    fn conditions_compare_levels_numbers_and_text() {
+        assert!(keeps("level>=warn", "level=error msg=x"));
+        assert!(!keeps("level>=warn", "level=info msg=x"));
+        assert!(keeps("status>=500", "status=503 msg=x"));
+        assert!(!keeps("status>=500", "status=60 msg=x"));
+        assert!(!keeps("user=bob", "status=200 msg=x"));
+        assert!(keeps("timeout", "level=warn msg=\"Timeout reached\""));
+        assert!(!keeps("Timeout", "level=warn msg=\"timeout reached\""));
+        assert!(keeps("\"a\" or (\"b\" and \"c\")", "msg=\"b c\" x=1"));
+        assert!(!keeps("\"a\" or (\"b\" and \"c\")", "msg=\"b\" x=1"));
+    }
+}
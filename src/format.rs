@@ -0,0 +1,622 @@
+//! `format` recognizes the structured line formats of log files and parses their
+//! lines into `Field`s.
+
+use crate::{
+    config::FormatConfig,
+    log_entry::{Field, LogEntry, LogLevel},
+    timestamp,
+};
+use regex::{Captures, Regex};
+use serde_json::{Map, Value};
+use std::{
+    path::Path,
+    sync::{Arc, LazyLock},
+};
+
+/// `DETECT_SAMPLE_SIZE` is how many entries from the start of a file are used to
+/// detect its format.
+const DETECT_SAMPLE_SIZE: usize = 50;
+
+/// `ACCESS_LOG` matches the Common Log Format of web servers like nginx and Apache,
+/// optionally followed by the referer and user agent of the Combined Log Format.
+static ACCESS_LOG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r#"^(?P<client>\S+) (?P<ident>\S+) (?P<user>\S+) \[(?P<time>[^\]]+)\] "#,
+        r#""(?P<request>(?:[^"\\]|\\.)*)" (?P<status>\d{3}) (?P<bytes>\d+|-)"#,
+        r#"(?: "(?P<referer>(?:[^"\\]|\\.)*)" "(?P<user_agent>(?:[^"\\]|\\.)*)")?"#,
+    ))
+    .expect("access log pattern is valid")
+});
+
+/// `SYSLOG_5424` matches the RFC 5424 syslog format.
+static SYSLOG_5424: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"^<(?P<pri>\d{1,3})>\d{1,2} (?P<time>\S+) (?P<host>\S+) (?P<app>\S+) (?P<pid>\S+) ",
+        r#"(?P<msgid>\S+) (?P<sd>-|(?:\[(?:[^\]"\\]|"(?:[^"\\]|\\.)*"|\\.)*\])+)(?: (?P<msg>.*))?$"#,
+    ))
+    .expect("RFC 5424 pattern is valid")
+});
+
+/// `SYSLOG_3164` matches the RFC 3164 syslog format of files in `/var/log`, with an
+/// optional priority and either the classic or an ISO 8601 timestamp.
+static SYSLOG_3164: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"^(?:<(?P<pri>\d{1,3})>)?",
+        r"(?P<time>[A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}|\d{4}-\d{2}-\d{2}T\S+) ",
+        r"(?P<host>\S+) (?P<app>[^\s\[:]+)(?:\[(?P<pid>\d+)\])?: ?(?P<msg>.*)$",
+    ))
+    .expect("RFC 3164 pattern is valid")
+});
+
+/// `SD_PARAM` matches a `name="value"` parameter of RFC 5424 structured data.
+static SD_PARAM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?P<name>[^\s=\]"]+)="(?P<value>(?:[^"\\]|\\.)*)""#)
+        .expect("structured data pattern is valid")
+});
+
+/// `FACILITIES` are the names of the syslog facilities, by number.
+const FACILITIES: [&str; 24] = [
+    "kern",
+    "user",
+    "mail",
+    "daemon",
+    "auth",
+    "syslog",
+    "lpr",
+    "news",
+    "uucp",
+    "cron",
+    "authpriv",
+    "ftp",
+    "ntp",
+    "security",
+    "console",
+    "solaris-cron",
+    "local0",
+    "local1",
+    "local2",
+    "local3",
+    "local4",
+    "local5",
+    "local6",
+    "local7",
+];
+
+/// `SEVERITIES` are the names of the syslog severities, by number. They are all
+/// names `LogLevel::parse` understands.
+const SEVERITIES: [&str; 8] = [
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+/// `CustomFormat` is a line format defined in the configuration file by a regex whose
+/// named groups become the fields of an entry.
+#[derive(Debug)]
+pub struct CustomFormat {
+    /// `name` is how the format is shown to the user.
+    name: String,
+    /// `pattern` matches a line, with a named group for every field.
+    pattern: Regex,
+    /// `timestamp` is the strftime-style format of the time field.
+    timestamp: Option<String>,
+    /// `files` matches the names of the files the format is used for without detecting
+    /// it. Formats without it take part in detection.
+    files: Option<glob::Pattern>,
+}
+
+impl CustomFormat {
+    /// Create the `CustomFormat` of a `[[formats]]` entry of the configuration file.
+    pub fn from_config(config: &FormatConfig) -> Result<Self, String> {
+        let pattern = Regex::new(&config.pattern)
+            .map_err(|e| format!("format '{}': invalid pattern: {e}", config.name))?;
+        if pattern.capture_names().flatten().next().is_none() {
+            return Err(format!(
+                "format '{}': the pattern has no named groups like (?P<msg>.*)",
+                config.name
+            ));
+        }
+        let files = config
+            .files
+            .as_deref()
+            .map(glob::Pattern::new)
+            .transpose()
+            .map_err(|e| format!("format '{}': invalid files glob: {e}", config.name))?;
+
+        Ok(Self {
+            name: config.name.clone(),
+            pattern,
+            timestamp: config.timestamp.clone(),
+            files,
+        })
+    }
+
+    /// Create the formats of the `[[formats]]` entries of the configuration file.
+    pub fn from_configs(configs: &[FormatConfig]) -> Result<Vec<Arc<Self>>, String> {
+        configs
+            .iter()
+            .map(|config| Self::from_config(config).map(Arc::new))
+            .collect()
+    }
+
+    /// `name` is how the format is shown to the user.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// `timestamp` is the strftime-style format of the time field, if set.
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.as_deref()
+    }
+
+    /// Check if the format is configured for the file at `path`. A glob with a `/`
+    /// is matched against the whole path, any other glob against the file name.
+    pub fn applies_to(&self, path: &str) -> bool {
+        let Some(files) = &self.files else {
+            return false;
+        };
+        if files.as_str().contains('/') {
+            return files.matches(path);
+        }
+        Path::new(path)
+            .file_name()
+            .is_some_and(|name| files.matches(&name.to_string_lossy()))
+    }
+
+    /// Turn the named groups matching `line` into fields.
+    fn parse(&self, line: &str) -> Vec<Field> {
+        let Some(captures) = self.pattern.captures(line) else {
+            return Vec::new();
+        };
+        self.pattern
+            .capture_names()
+            .flatten()
+            .filter_map(|name| {
+                let capture = captures.name(name)?;
+                Some(Field::new(name, capture.as_str()).at(capture.range()))
+            })
+            .collect()
+    }
+}
+
+/// `LogFormat` is the format of the lines in a log file.
+#[derive(Debug, Clone)]
+pub enum LogFormat {
+    /// `Plain` is unstructured text without fields.
+    Plain,
+    /// `Logfmt` is `key=value` pairs, like `level=info msg="started" port=8080`.
+    Logfmt,
+    /// `Json` is a JSON object on every line, like `{"level":"info","msg":"started"}`.
+    Json,
+    /// `Access` is the Common or Combined Log Format of web server access logs.
+    Access,
+    /// `Syslog` is the RFC 5424 or RFC 3164 syslog format.
+    Syslog,
+    /// `Custom` is a format defined in the configuration file.
+    Custom(Arc<CustomFormat>),
+}
+
+impl LogFormat {
+    /// `DETECTABLE` are the formats tried when detecting the format of a file, most
+    /// specific first.
+    const DETECTABLE: [Self; 4] = [Self::Json, Self::Access, Self::Syslog, Self::Logfmt];
+
+    /// `name` is how the format is shown to the user.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Plain => "plain",
+            Self::Logfmt => "logfmt",
+            Self::Json => "json",
+            Self::Access => "access",
+            Self::Syslog => "syslog",
+            Self::Custom(custom) => custom.name(),
+        }
+    }
+
+    /// Detect the format of a file from the first lines of its entries. A format is
+    /// picked when most of the lines are in it. The `custom` formats are tried before
+    /// the built-in ones.
+    pub fn detect<'a>(
+        lines: impl IntoIterator<Item = &'a str>,
+        custom: &[Arc<CustomFormat>],
+    ) -> Self {
+        let lines: Vec<&str> = lines
+            .into_iter()
+            .filter(|line| !line.trim().is_empty())
+            .take(DETECT_SAMPLE_SIZE)
+            .collect();
+        if lines.is_empty() {
+            return Self::Plain;
+        }
+
+        custom
+            .iter()
+            .map(|custom| Self::Custom(Arc::clone(custom)))
+            .chain(Self::DETECTABLE)
+            .find(|format| {
+                let matching = lines.iter().filter(|line| format.matches(line)).count();
+                matching * 2 > lines.len()
+            })
+            .unwrap_or(Self::Plain)
+    }
+
+    /// Check if `line` is in this format.
+    pub fn matches(&self, line: &str) -> bool {
+        match self {
+            Self::Plain => true,
+            Self::Logfmt => {
+                let fields = parse_logfmt(line);
+                let pairs = fields
+                    .iter()
+                    .filter(|f| f.key_range.is_some() && f.value_range.is_some())
+                    .count();
+                pairs >= 2 && pairs * 2 > fields.len()
+            }
+            Self::Json => {
+                line.trim_start().starts_with('{')
+                    && serde_json::from_str::<Value>(line).is_ok_and(|value| value.is_object())
+            }
+            Self::Access => ACCESS_LOG.is_match(line),
+            Self::Syslog => SYSLOG_5424.is_match(line) || SYSLOG_3164.is_match(line),
+            Self::Custom(custom) => custom.pattern.is_match(line),
+        }
+    }
+
+    /// Parse the fields of `line`.
+    pub fn parse(&self, line: &str) -> Vec<Field> {
+        match self {
+            Self::Plain => Vec::new(),
+            Self::Logfmt => parse_logfmt(line),
+            Self::Json => parse_json(line),
+            Self::Access => ACCESS_LOG
+                .captures(line)
+                .map(|captures| parse_access(&captures))
+                .unwrap_or_default(),
+            Self::Syslog => SYSLOG_5424
+                .captures(line)
+                .or_else(|| SYSLOG_3164.captures(line))
+                .map(|captures| parse_syslog(&captures))
+                .unwrap_or_default(),
+            Self::Custom(custom) => custom.parse(line),
+        }
+    }
+
+    /// The level of an entry that the format decides on, rather than a level field.
+    fn level(&self, entry: &LogEntry) -> Option<LogLevel> {
+        match self {
+            // Rows are colored and filtered by the class of their status code
+            Self::Access => match entry.field("status")?.as_bytes().first()? {
+                b'5' => Some(LogLevel::Error),
+                b'4' => Some(LogLevel::Warn),
+                b'3' => Some(LogLevel::Debug),
+                _ => Some(LogLevel::Info),
+            },
+            Self::Plain | Self::Logfmt | Self::Json | Self::Syslog | Self::Custom(_) => None,
+        }
+    }
+
+    /// The strftime-style format of the time field, when the format has one.
+    fn timestamp_format(&self) -> Option<&str> {
+        match self {
+            Self::Custom(custom) => custom.timestamp(),
+            _ => None,
+        }
+    }
+
+    /// Create the `LogEntry` for `content` found at `line`, with the fields of its
+    /// first line and the time of its time field, or the time its first line starts
+    /// with when it has no time field.
+    pub fn entry(&self, line: usize, content: String) -> LogEntry {
+        let mut entry = LogEntry::new(line, content);
+        let fields = self.parse(entry.content.lines().next().unwrap_or_default());
+        if !fields.is_empty() {
+            entry.set_fields(fields);
+        }
+        if let Some(level) = self.level(&entry) {
+            entry.level = Some(level);
+        }
+        entry.timestamp = match entry.time() {
+            Some(time) => timestamp::parse(time, self.timestamp_format()),
+            None => entry
+                .content
+                .lines()
+                .next()
+                .and_then(timestamp::leading)
+                .map(|(time, _)| time),
+        };
+        entry
+    }
+}
+
+/// Parse the `key=value` pairs of a logfmt line. Values can be quoted with `"` and
+/// contain escaped quotes, and a key without a value is a field with an empty value.
+fn parse_logfmt(line: &str) -> Vec<Field> {
+    let bytes = line.as_bytes();
+    let mut fields = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i].is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let key_start = i;
+        while i < bytes.len() && !bytes[i].is_ascii_whitespace() && bytes[i] != b'=' {
+            i += 1;
+        }
+        let key_range = key_start..i;
+
+        if key_range.is_empty() || bytes[key_start] == b'"' {
+            // Not a key, skip the whole token
+            while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+                i += 1;
+            }
+            continue;
+        }
+
+        let mut field = Field::new(&line[key_range.clone()], "");
+        field.key_range = Some(key_range);
+
+        if bytes.get(i) == Some(&b'=') {
+            i += 1;
+            if bytes.get(i) == Some(&b'"') {
+                let value_start = i + 1;
+                let mut value = String::new();
+                let mut chars = line[value_start..].char_indices();
+                let mut end = line.len();
+                while let Some((offset, c)) = chars.next() {
+                    match c {
+                        '"' => {
+                            end = value_start + offset;
+                            break;
+                        }
+                        '\\' => match chars.next() {
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, 't')) => value.push('\t'),
+                            Some((_, escaped)) => value.push(escaped),
+                            None => {}
+                        },
+                        c => value.push(c),
+                    }
+                }
+                field.value = value;
+                field.value_range = Some(value_start..end);
+                i = (end + 1).min(line.len());
+            } else {
+                let value_start = i;
+                while i < bytes.len() && !bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+                field.value = line[value_start..i].to_owned();
+                field.value_range = Some(value_start..i);
+            }
+        }
+
+        fields.push(field);
+    }
+
+    fields
+}
+
+/// Turn the members of a JSON object line into fields. Nested objects are flattened
+/// into fields named after their path, like `user.id`, and arrays keep their JSON text.
+fn parse_json(line: &str) -> Vec<Field> {
+    let Ok(Value::Object(object)) = serde_json::from_str(line) else {
+        return Vec::new();
+    };
+    let mut fields = Vec::new();
+    flatten_json("", &object, &mut fields);
+    fields
+}
+
+/// Add the members of `object` to `fields`, with their names prefixed by `prefix`.
+fn flatten_json(prefix: &str, object: &Map<String, Value>, fields: &mut Vec<Field>) {
+    for (key, value) in object {
+        let name = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            Value::Object(inner) => flatten_json(&name, inner, fields),
+            Value::String(text) => fields.push(Field::new(name, text.as_str())),
+            other => fields.push(Field::new(name, other.to_string())),
+        }
+    }
+}
+
+/// Turn the captures of an access log line into fields, splitting the request into
+/// its method, path and protocol.
+fn parse_access(captures: &Captures) -> Vec<Field> {
+    let mut fields = Vec::new();
+    let mut push = |name: &str, range: std::ops::Range<usize>, value: &str| {
+        // `-` is how the format writes a missing value
+        if value != "-" {
+            fields.push(Field::new(name, value.replace("\\\"", "\"")).at(range));
+        }
+    };
+
+    for name in ["client", "ident", "user", "time"] {
+        if let Some(capture) = captures.name(name) {
+            push(name, capture.range(), capture.as_str());
+        }
+    }
+    if let Some(request) = captures.name("request") {
+        let mut start = request.start();
+        for (name, part) in ["method", "path", "protocol"]
+            .into_iter()
+            .zip(request.as_str().splitn(3, ' '))
+        {
+            push(name, start..start + part.len(), part);
+            start += part.len() + 1;
+        }
+    }
+    for name in ["status", "bytes", "referer", "user_agent"] {
+        if let Some(capture) = captures.name(name) {
+            push(name, capture.range(), capture.as_str());
+        }
+    }
+    fields
+}
+
+/// Turn the captures of a syslog line into fields. The priority becomes the facility
+/// and severity, and every structured data parameter becomes a field named after its
+/// element and parameter, like `origin.ip`.
+fn parse_syslog(captures: &Captures) -> Vec<Field> {
+    let mut fields = Vec::new();
+
+    if let Some(pri) = captures
+        .name("pri")
+        .and_then(|pri| pri.as_str().parse::<usize>().ok())
+    {
+        if let Some(facility) = FACILITIES.get(pri / 8) {
+            fields.push(Field::new("facility", *facility));
+        }
+        fields.push(Field::new("severity", SEVERITIES[pri % 8]));
+    }
+
+    for name in ["time", "host", "app", "pid", "msgid"] {
+        if let Some(capture) = captures.name(name).filter(|c| c.as_str() != "-") {
+            fields.push(Field::new(name, capture.as_str()).at(capture.range()));
+        }
+    }
+
+    if let Some(sd) = captures.name("sd").filter(|sd| sd.as_str() != "-") {
+        for element in sd.as_str().trim_matches(['[', ']']).split("][") {
+            let id = element.split_whitespace().next().unwrap_or_default();
+            for param in SD_PARAM.captures_iter(element) {
+                let value = param["value"]
+                    .replace("\\\"", "\"")
+                    .replace("\\]", "]")
+                    .replace("\\\\", "\\");
+                fields.push(Field::new(format!("{id}.{}", &param["name"]), value));
+            }
+        }
+    }
+
+    if let Some(msg) = captures.name("msg") {
+        // RFC 5424 messages may start with a byte order mark
+        let text = msg.as_str().trim_start_matches('\u{feff}');
+        let start = msg.end() - text.len();
+        fields.push(Field::new("msg", text).at(start..msg.end()));
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    /// The format detected for a file of `line`s, and the entry made of `line`.
+    fn read(line: &str, custom: &[Arc<CustomFormat>]) -> (String, LogEntry) {
+        let format = LogFormat::detect([line, line], custom);
+        let entry = format.entry(0, line.to_owned());
+        (format.name().to_owned(), entry)
+    }
+
+    #[test]
+    fn plain() {
+        let (name, entry) = read("2024-05-01 10:00:00,123 ERROR retry failed", &[]);
+        assert_eq!(name, "plain");
+        assert!(entry.fields.is_empty());
+        assert_eq!(entry.level, Some(LogLevel::Error));
+        assert_eq!(
+            entry.timestamp,
+            timestamp::parse("2024-05-01 10:00:00,123", None)
+        );
+    }
+
+    #[test]
+    fn logfmt() {
+        let line = r#"time=2024-05-01T10:00:00Z level=warn msg="disk \"sda\" full" retries=3 dry"#;
+        let (name, entry) = read(line, &[]);
+        assert_eq!(name, "logfmt");
+        assert_eq!(entry.level, Some(LogLevel::Warn));
+        assert_eq!(entry.message(), Some(r#"disk "sda" full"#));
+        assert_eq!(entry.field("retries"), Some("3"));
+        assert_eq!(entry.field("dry"), Some(""));
+        assert_eq!(
+            entry.timestamp,
+            DateTime::parse_from_rfc3339("2024-05-01T10:00:00Z").ok()
+        );
+        let range = entry.fields[2].value_range.clone().unwrap_or_default();
+        assert_eq!(&line[range], r#"disk \"sda\" full"#);
+    }
+
+    #[test]
+    fn json() {
+        let line =
+            r#"{"ts":1700000000,"level":"ERROR","msg":"boom","user":{"id":42},"tags":["a"]}"#;
+        let (name, entry) = read(line, &[]);
+        assert_eq!(name, "json");
+        assert_eq!(entry.level, Some(LogLevel::Error));
+        assert_eq!(entry.message(), Some("boom"));
+        assert_eq!(entry.field("user.id"), Some("42"));
+        assert_eq!(entry.field("tags"), Some(r#"["a"]"#));
+        assert_eq!(
+            entry.timestamp.map(|time| time.timestamp()),
+            Some(1_700_000_000)
+        );
+    }
+
+    #[test]
+    fn access() {
+        let line = concat!(
+            r#"203.0.113.9 - - [01/May/2024:10:00:00 +0000] "GET /api/users?id=1 HTTP/1.1" "#,
+            r#"503 1234 "-" "curl/8.0""#,
+        );
+        let (name, entry) = read(line, &[]);
+        assert_eq!(name, "access");
+        assert_eq!(entry.level, Some(LogLevel::Error));
+        assert_eq!(entry.field("client"), Some("203.0.113.9"));
+        assert_eq!(entry.field("user"), None);
+        assert_eq!(entry.field("method"), Some("GET"));
+        assert_eq!(entry.field("path"), Some("/api/users?id=1"));
+        assert_eq!(entry.field("status"), Some("503"));
+        assert_eq!(entry.field("user_agent"), Some("curl/8.0"));
+        assert_eq!(
+            entry.timestamp,
+            DateTime::parse_from_rfc3339("2024-05-01T10:00:00Z").ok()
+        );
+    }
+
+    #[test]
+    fn syslog() {
+        let line = concat!(
+            r#"<165>1 2024-05-01T10:00:00.003Z web1 app 4242 ID47 "#,
+            r#"[origin ip="192.0.2.1"] started"#,
+        );
+        let (name, entry) = read(line, &[]);
+        assert_eq!(name, "syslog");
+        assert_eq!(entry.field("facility"), Some("local4"));
+        assert_eq!(entry.level, Some(LogLevel::Info));
+        assert_eq!(entry.field("host"), Some("web1"));
+        assert_eq!(entry.field("origin.ip"), Some("192.0.2.1"));
+        assert_eq!(entry.message(), Some("started"));
+
+        let (name, entry) = read("May  1 10:00:00 web1 sshd[99]: Accepted key", &[]);
+        assert_eq!(name, "syslog");
+        assert_eq!(entry.field("app"), Some("sshd"));
+        assert_eq!(entry.field("pid"), Some("99"));
+        assert_eq!(entry.message(), Some("Accepted key"));
+        assert!(entry.timestamp.is_some());
+    }
+
+    #[test]
+    fn custom() {
+        let config = FormatConfig {
+            name: "pipes".to_owned(),
+            pattern: r"^(?P<time>[^|]+)\|(?P<level>\w+)\|(?P<msg>.*)$".to_owned(),
+            timestamp: Some("%d.%m.%Y %H:%M".to_owned()),
+            files: Some("*.pipes".to_owned()),
+        };
+        let custom = vec![Arc::new(CustomFormat::from_config(&config).unwrap())];
+        let (name, entry) = read("01.05.2024 10:00|warn|slow query", &custom);
+        assert_eq!(name, "pipes");
+        assert_eq!(entry.level, Some(LogLevel::Warn));
+        assert_eq!(entry.message(), Some("slow query"));
+        assert_eq!(entry.timestamp, timestamp::parse("2024-05-01 10:00", None));
+        assert!(custom[0].applies_to("/var/log/app.pipes"));
+        assert!(!custom[0].applies_to("/var/log/app.log"));
+    }
+}
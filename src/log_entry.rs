@@ -1,17 +1,234 @@
 //! `log_entry` represents a row in a `LogFile`
 
+use crate::{ansi, timestamp::Timestamp};
+use std::{fmt, ops::Range};
+
+/// `LogLevel` is the severity of a `LogEntry`, ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// `Trace` is the most verbose level.
+    Trace,
+    /// `Debug` is for diagnostic messages.
+    Debug,
+    /// `Info` is for normal operation.
+    Info,
+    /// `Warn` is for unexpected but recoverable situations.
+    Warn,
+    /// `Error` is for failures.
+    Error,
+    /// `Fatal` is for failures that stop the application.
+    Fatal,
+}
+
+impl LogLevel {
+    /// `ALL` is every level, from least to most severe.
+    pub const ALL: [Self; 6] = [
+        Self::Trace,
+        Self::Debug,
+        Self::Info,
+        Self::Warn,
+        Self::Error,
+        Self::Fatal,
+    ];
+
+    /// Parse a level name like `warn`, `WARNING` or `err`, ignoring case.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "trace" | "trc" => Some(Self::Trace),
+            "debug" | "dbg" => Some(Self::Debug),
+            "info" | "inf" | "information" | "notice" => Some(Self::Info),
+            "warn" | "wrn" | "warning" => Some(Self::Warn),
+            "error" | "err" => Some(Self::Error),
+            "fatal" | "ftl" | "critical" | "crit" | "panic" | "emerg" | "alert" => {
+                Some(Self::Fatal)
+            }
+            _ => None,
+        }
+    }
+
+    /// Detect the level of a plain text log line. Level names are only recognized
+    /// when written in uppercase (`ERROR`, `[WARN]`) or as the value of a level key
+    /// (`level=info`, `"level":"debug"`), so that words in messages don't count.
+    pub fn detect(content: &str) -> Option<Self> {
+        let lower = content.to_ascii_lowercase();
+        for key in ["level=", "\"level\":\"", "\"level\": \"", "lvl="] {
+            if let Some(start) = lower.find(key) {
+                let value: String = content[start + key.len()..]
+                    .chars()
+                    .take_while(char::is_ascii_alphabetic)
+                    .collect();
+                if let Some(level) = Self::parse(&value) {
+                    return Some(level);
+                }
+            }
+        }
+
+        content
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .filter(|word| word.len() >= 3 && word.chars().all(|c| c.is_ascii_uppercase()))
+            .find_map(Self::parse)
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Trace => "TRACE",
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+            Self::Fatal => "FATAL",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// `TIME_FIELDS` are the field names holding the time of an entry, in order of preference.
+const TIME_FIELDS: [&str; 5] = ["time", "ts", "timestamp", "@timestamp", "t"];
+
+/// `MESSAGE_FIELDS` are the field names holding the message of an entry, in order of preference.
+const MESSAGE_FIELDS: [&str; 3] = ["msg", "message", "@message"];
+
+/// `LEVEL_FIELDS` are the field names holding the level of an entry, in order of preference.
+const LEVEL_FIELDS: [&str; 4] = ["level", "lvl", "severity", "loglevel"];
+
+/// `Field` is a named value parsed out of a structured `LogEntry`, like `user=42` in logfmt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    /// `name` is the key of the field.
+    pub name: String,
+    /// `value` is the value of the field, unquoted.
+    pub value: String,
+    /// `key_range` is where the key is written in the first line of the entry, if it is.
+    pub key_range: Option<Range<usize>>,
+    /// `value_range` is where the value is written in the first line of the entry.
+    pub value_range: Option<Range<usize>>,
+}
+
+impl Field {
+    /// Create a new `Field` that is not highlighted in the content.
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+            key_range: None,
+            value_range: None,
+        }
+    }
+
+    /// Set where the value is written in the content.
+    pub fn at(mut self, value_range: Range<usize>) -> Self {
+        self.value_range = Some(value_range);
+        self
+    }
+}
+
 /// `LogEntry` represents a row in a `LogFile`.
 #[derive(Debug, Clone)]
 pub struct LogEntry {
-    /// `line` is the line number where the content was found in the log file.
+    /// `line` is the line number where the content was found in the log file. For
+    /// entries spanning several lines, it is the number of the first line.
     pub line: usize,
-    /// `content` is the actual string content of the log message.
+    /// `content` is the actual string content of the log message, without any
+    /// escape sequences. The lines of a multiline entry are separated by newlines.
     pub content: String,
+    /// `raw` is the content as it was in the log file when it had escape sequences.
+    pub raw: Option<String>,
+    /// `level` is the severity detected from the content, if any.
+    pub level: Option<LogLevel>,
+    /// `fields` are the structured fields parsed from the first line, in order.
+    pub fields: Vec<Field>,
+    /// `timestamp` is the time read from the time field, if it could be read.
+    pub timestamp: Option<Timestamp>,
 }
 
 impl LogEntry {
     /// Create a new `LogEntry`
-    pub const fn new(line: usize, content: String) -> Self {
-        Self { line, content }
+    pub fn new(line: usize, content: String) -> Self {
+        let (content, raw) = if ansi::has_escapes(&content) {
+            (ansi::strip(&content), Some(content))
+        } else {
+            (content, None)
+        };
+        // The first line of a stack trace says how bad it is, the rest is only a fallback
+        let level = content
+            .lines()
+            .next()
+            .and_then(LogLevel::detect)
+            .or_else(|| LogLevel::detect(&content));
+        Self {
+            line,
+            content,
+            raw,
+            level,
+            fields: Vec::new(),
+            timestamp: None,
+        }
+    }
+
+    /// Set the structured fields of the entry, taking the level from a level field
+    /// when there is one.
+    pub fn set_fields(&mut self, fields: Vec<Field>) {
+        self.fields = fields;
+        if let Some(level) = LEVEL_FIELDS
+            .iter()
+            .find_map(|&name| self.field(name))
+            .and_then(LogLevel::parse)
+        {
+            self.level = Some(level);
+        }
+    }
+
+    /// The value of the field called `name`.
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|field| field.name == name)
+            .map(|field| field.value.as_str())
+    }
+
+    /// The time of the entry, from the first well-known time field.
+    pub fn time(&self) -> Option<&str> {
+        TIME_FIELDS.iter().find_map(|&name| self.field(name))
+    }
+
+    /// The name of the first well-known time field of the entry.
+    pub fn time_field(&self) -> Option<&'static str> {
+        TIME_FIELDS
+            .into_iter()
+            .find(|&name| self.field(name).is_some())
+    }
+
+    /// The name of the first well-known message field of the entry.
+    pub fn message_field(&self) -> Option<&'static str> {
+        MESSAGE_FIELDS
+            .into_iter()
+            .find(|&name| self.field(name).is_some())
+    }
+
+    /// The message of the entry, from the first well-known message field.
+    pub fn message(&self) -> Option<&str> {
+        MESSAGE_FIELDS.iter().find_map(|&name| self.field(name))
+    }
+
+    /// Check if the field called `name` is the level, time or message of the entry,
+    /// which are shown on their own instead of with the other fields.
+    pub fn is_well_known(name: &str) -> bool {
+        TIME_FIELDS
+            .iter()
+            .chain(&MESSAGE_FIELDS)
+            .chain(&LEVEL_FIELDS)
+            .any(|&known| known == name)
+    }
+
+    /// The number of lines folded into the entry.
+    pub fn line_count(&self) -> usize {
+        self.content.matches('\n').count() + 1
+    }
+
+    /// The line number of the last line of the entry.
+    pub fn last_line(&self) -> usize {
+        self.line + self.line_count() - 1
     }
 }
@@ -1,17 +1,18 @@
 //! `log_file` is a module that contains abstractions for a `LogFile` type.
 
-use std::path::{Path, PathBuf};
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use crate::log_entry::LogEntry;
-
-/// `LogFileExtension` contains the supported extensions for log files.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
-pub enum LogFileExtension {
-    /// `Log` represents the plaintext ".log" extension.
-    Log,
-    /// `Json` represents JavaScript Object Notation.
-    Json,
-}
+use crate::{
+    format::{CustomFormat, LogFormat},
+    log_entry::LogEntry,
+    multiline::Multiline,
+    persistence,
+};
 
 /// `LogFile` is a file that contains application logs.
 #[derive(Debug, Clone)]
@@ -19,11 +20,34 @@ pub struct LogFile {
     /// `path` is the path to the file.
     path: String,
 
-    /// `extension` is the type of extension this file has.
-    extension: LogFileExtension,
+    /// `offset` is the byte position in the file after the last complete line read.
+    offset: u64,
+
+    /// `next_line` is the line number of the line at `offset`.
+    next_line: usize,
+
+    /// `multiline` folds continuation lines into the entry before them when set.
+    multiline: Option<Multiline>,
+
+    /// `format` is the format of the lines, detected from the first entries read
+    /// unless it was set.
+    format: Option<LogFormat>,
 
-    /// `current_line` is the current cursor position of the log file.
-    current_line: usize,
+    /// `custom_formats` are the formats from the configuration file tried before the
+    /// built-in ones when detecting the format.
+    custom_formats: Vec<Arc<CustomFormat>>,
+
+    /// `last_group` is the line number and lines of the last entry read, which
+    /// continuation lines appended to the file still belong to.
+    last_group: Option<(usize, Vec<String>)>,
+
+    /// `partial` is set when the last entry read ends with a line the writer isn't
+    /// done with, which is read again next time.
+    partial: bool,
+
+    /// `inode` identifies the file read so far, to notice when another file takes
+    /// its place.
+    inode: u64,
 }
 
 impl LogFile {
@@ -31,14 +55,22 @@ impl LogFile {
     pub fn new(path: String) -> Result<Self, String> {
         let mut log = Self {
             path,
-            extension: LogFileExtension::Log,
-            current_line: 0,
+            offset: 0,
+            next_line: 0,
+            multiline: None,
+            format: None,
+            custom_formats: Vec::new(),
+            last_group: None,
+            partial: false,
+            inode: 0,
         };
 
         log.expand_path()?;
         log.check_exists()?;
         log.check_is_file()?;
-        log.set_extension()?;
+        log.inode = fs::metadata(&log.path)
+            .map(|metadata| persistence::inode(&metadata))
+            .map_err(|e| e.to_string())?;
 
         Ok(log)
     }
@@ -73,51 +105,149 @@ impl LogFile {
         Ok(())
     }
 
-    /// `set_extension` determines the file extension and sets the internal representation.
-    fn set_extension(&mut self) -> Result<(), String> {
-        let path = Path::new(&self.path);
-        let ext = match path.extension() {
-            Some(os_str) => {
-                let extension = os_str.to_string_lossy().to_string();
-                match extension.as_str() {
-                    "json" => LogFileExtension::Json,
-                    "log" => LogFileExtension::Log,
-                    _ => return Err("extension not supported".into()),
-                }
-            }
-            _ => return Err("extension not supported".into()),
+    /// `path` is the expanded path of the log file.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Set the rules that fold continuation lines into one entry, or `None` to make
+    /// every line its own entry.
+    pub fn set_multiline(&mut self, multiline: Option<Multiline>) {
+        self.multiline = multiline;
+    }
+
+    /// Set the format of the lines instead of detecting it.
+    pub fn set_format(&mut self, format: LogFormat) {
+        self.format = Some(format);
+    }
+
+    /// Set the formats from the configuration file. A format configured for the file's
+    /// name is used right away, the others are tried when detecting the format.
+    pub fn set_custom_formats(&mut self, formats: &[Arc<CustomFormat>]) {
+        if let Some(format) = formats.iter().find(|format| format.applies_to(&self.path)) {
+            self.set_format(LogFormat::Custom(Arc::clone(format)));
+        }
+        self.custom_formats = formats.to_vec();
+    }
+
+    /// `format` is the format of the lines, once known.
+    pub const fn format(&self) -> Option<&LogFormat> {
+        self.format.as_ref()
+    }
+
+    /// Start reading the file over from the beginning when it was truncated or
+    /// replaced by a new file at the path, like after log rotation. Returns whether it
+    /// was, and `Ok(false)` while no file is at the path.
+    pub fn reset_if_rotated(&mut self) -> Result<bool, String> {
+        let metadata = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.to_string()),
         };
+        let inode = persistence::inode(&metadata);
+        if inode == self.inode && metadata.len() >= self.offset {
+            return Ok(false);
+        }
 
-        self.extension = ext;
+        self.inode = inode;
+        self.offset = 0;
+        self.next_line = 0;
+        self.last_group = None;
+        Ok(true)
+    }
 
-        Ok(())
+    /// Whether the last entry returned by `get_entries` ends with a line the writer
+    /// isn't done with. It is returned again, complete, by a later call.
+    pub const fn ends_with_partial_line(&self) -> bool {
+        self.partial
     }
 
-    /// `get_entries` gets all of the current log file entries in the file.
-    /// After iteration of all lines, it updates the internal state of the log file to
-    /// know where we left off for the next read.
+    /// `get_entries` gets the entries added to the file since the last call, starting
+    /// where the previous read left off.
+    ///
+    /// When the lines read continue the last entry of the previous call, that entry is
+    /// returned again with the new lines added, as the first entry. A line without a
+    /// trailing newline yet is returned too, but read again next time since the writer
+    /// may not be done with it. Entries returned again keep their line number, so they
+    /// replace any entry from an earlier call starting at or after it.
     pub fn get_entries(&mut self) -> Result<Vec<LogEntry>, String> {
-        use std::fs::File;
-        use std::io::{BufRead, BufReader};
-        let path = self.path.clone();
-        let file = File::open(&path).map_err(|e| e.to_string())?;
-        let reader = BufReader::new(file);
-        let mut entries: Vec<LogEntry> = Vec::new();
-
-        for (i, line) in reader.lines().enumerate() {
-            if i > self.current_line {
-                let line = line.map_err(|e| e.to_string())?;
-                entries.push(LogEntry::new(i, line));
+        let file = File::open(&self.path).map_err(|e| e.to_string())?;
+        let mut reader = BufReader::new(file);
+        reader
+            .seek(SeekFrom::Start(self.offset))
+            .map_err(|e| e.to_string())?;
+
+        let previous = self.last_group.take();
+        let was_partial = self.partial;
+        let previous_len = previous.as_ref().map(|(_, lines)| lines.len());
+        let mut groups: Vec<(usize, Vec<String>)> = previous.into_iter().collect();
+
+        let mut buffer = Vec::new();
+        let mut partial = None;
+        loop {
+            buffer.clear();
+            let read = reader
+                .read_until(b'\n', &mut buffer)
+                .map_err(|e| e.to_string())?;
+            if read == 0 {
+                break;
+            }
+
+            let line = String::from_utf8_lossy(&buffer)
+                .trim_end_matches(['\n', '\r'])
+                .to_owned();
+            if buffer.last() != Some(&b'\n') {
+                partial = Some(line);
+                break;
             }
+
+            self.offset += read as u64;
+            self.push_line(&mut groups, self.next_line, line);
+            self.next_line += 1;
         }
 
-        self.current_line = entries
-            .iter()
-            .max_by_key(|ent| ent.line)
-            .map(|ent| ent.line)
-            .unwrap_or_default();
+        self.last_group = groups.last().cloned();
+        self.partial = partial.is_some();
+        if let Some(line) = partial {
+            self.push_line(&mut groups, self.next_line, line);
+        }
 
-        Ok(entries)
+        // The last entry of the previous call only needs to be returned again if it grew,
+        // or if a partial line was returned with it, which may now start an entry instead
+        if !was_partial
+            && previous_len.is_some()
+            && groups.first().map(|(_, lines)| lines.len()) == previous_len
+        {
+            groups.remove(0);
+        }
+
+        let format = match &self.format {
+            Some(format) => format,
+            None if groups.is_empty() => return Ok(Vec::new()),
+            None => self.format.insert(LogFormat::detect(
+                groups
+                    .iter()
+                    .filter_map(|(_, lines)| lines.first().map(String::as_str)),
+                &self.custom_formats,
+            )),
+        };
+
+        Ok(groups
+            .into_iter()
+            .map(|(line, lines)| format.entry(line, lines.join("\n")))
+            .collect())
+    }
+
+    /// Add `line` to the last group when it continues it, otherwise start a new group.
+    fn push_line(&self, groups: &mut Vec<(usize, Vec<String>)>, number: usize, line: String) {
+        let continues = self
+            .multiline
+            .as_ref()
+            .is_some_and(|multiline| multiline.is_continuation(&line));
+        match groups.last_mut() {
+            Some((_, lines)) if continues => lines.push(line),
+            _ => groups.push((number, vec![line])),
+        }
     }
 }
 
@@ -1,8 +1,34 @@
 //! `log_viewer` is the module for `LogViewer` logic.
 
-use crate::{Args, Commands, log_entry::LogEntry, log_file::LogFile, tui::Tui};
+use crate::{
+    ApplicationAction, Args, Commands, FormatAction, PrintArgs,
+    config::{Config, FormatConfig},
+    event::{AppEvent, EventLoop},
+    export::{self, ExportFormat},
+    filter::Filter,
+    format::{CustomFormat, LogFormat},
+    log_entry::{LogEntry, LogLevel},
+    log_file::LogFile,
+    multiline::Multiline,
+    persistence::{FileRecord, Note, Registry},
+    printer::Printer,
+    stats::Stats,
+    timestamp,
+    tui::{self, Clipboard, KeyMap, Theme, Tui, TuiRequest},
+};
 use notify::{Event, EventKind, RecursiveMode, Watcher};
-use std::{io, path::PathBuf, sync::mpsc::channel};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    process,
+    sync::{Arc, mpsc},
+    time::Duration,
+};
+
+/// `TICK_RATE` is how often the timer thread sends a `Tick` to the main loop.
+const TICK_RATE: Duration = Duration::from_millis(250);
 
 /// `LogViewer` manages the viewing of log files.
 #[derive(Debug)]
@@ -26,8 +52,19 @@ impl LogViewer {
 
         match (command, file_path) {
             (Some(commands), None) => self.run_commands(&commands),
+            (None, Some(file_path)) if self.args.no_tui || !io::stdout().is_terminal() => {
+                let query = self.args.query.as_deref();
+                match Self::follow_without_tui(file_path, query, self.args.lines) {
+                    Err(e) if !is_broken_pipe(&e) => {
+                        eprintln!("Follow error: {e}");
+                        std::process::exit(1);
+                    }
+                    _ => {}
+                }
+            }
             (None, Some(file_path)) => {
-                if let Err(e) = Self::run_single_file_with_tui(file_path) {
+                let query = self.args.query.as_deref();
+                if let Err(e) = Self::run_single_file_with_tui(file_path, query) {
                     eprintln!("TUI error: {e}");
                     std::process::exit(1);
                 }
@@ -41,20 +78,470 @@ impl LogViewer {
 
     /// run the application using the provided commands
     pub fn run_commands(&self, commands: &Commands) {
-        _ = commands;
-        todo!(
-            "Commands are not yet implemented, please use the application in single-file mode for now."
-        )
+        match commands {
+            Commands::Application { action } => {
+                if let Err(e) = Self::run_application_action(action) {
+                    eprintln!("Application error: {e}");
+                    std::process::exit(1);
+                }
+            }
+            Commands::Cat(args) => match Self::print_entries(args) {
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!("Cat error: {e}");
+                    std::process::exit(2);
+                }
+            },
+            Commands::Grep(args) => {
+                if args.query.is_none() && args.since.is_none() {
+                    eprintln!("Grep error: give a --query or --since to match entries with");
+                    std::process::exit(2);
+                }
+                match Self::print_entries(args) {
+                    Ok(true) => {}
+                    Ok(false) => std::process::exit(1),
+                    Err(e) => {
+                        eprintln!("Grep error: {e}");
+                        std::process::exit(2);
+                    }
+                }
+            }
+            Commands::Stats {
+                file,
+                query,
+                top,
+                json,
+            } => {
+                if let Err(e) = Self::print_stats(file.clone(), query.as_deref(), *top, *json) {
+                    eprintln!("Stats error: {e}");
+                    std::process::exit(1);
+                }
+            }
+            Commands::Export {
+                file,
+                output,
+                format,
+                query,
+                columns,
+            } => {
+                let format = format.as_deref();
+                let query = query.as_deref();
+                if let Err(e) =
+                    Self::export(file.clone(), output.as_deref(), format, query, columns)
+                {
+                    eprintln!("Export error: {e}");
+                    std::process::exit(1);
+                }
+            }
+            Commands::Format {
+                action:
+                    FormatAction::Test {
+                        format,
+                        file,
+                        timestamp,
+                        entries,
+                    },
+            } => match Self::test_format(format, file.clone(), timestamp.clone(), *entries) {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("Format error: {e}");
+                    std::process::exit(2);
+                }
+            },
+        }
+    }
+
+    /// Add, list or remove registered applications.
+    fn run_application_action(action: &ApplicationAction) -> Result<(), String> {
+        let mut registry = Registry::load()?;
+        match action {
+            ApplicationAction::Add { name, directory } => {
+                let application = registry.add(name, directory)?;
+                println!("added {name} ({})", application.directory);
+                registry.save()?;
+            }
+            ApplicationAction::List => {
+                if registry.applications.is_empty() {
+                    println!("no applications registered");
+                }
+                let width = registry.applications.keys().map(String::len).max();
+                for (name, application) in &registry.applications {
+                    let width = width.unwrap_or_default();
+                    println!("{name:<width$}  {}", application.directory);
+                }
+            }
+            ApplicationAction::Remove { name } => {
+                registry.remove(name)?;
+                println!("removed {name}");
+                registry.save()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the format called `format` in the configuration file, or the regex
+    /// `format`, against the entries of the file at `file_path`. The fields of the
+    /// first `shown` entries are printed, followed by how many entries matched.
+    /// Returns whether every entry matched and had a time that could be read.
+    fn test_format(
+        format: &str,
+        file_path: String,
+        timestamp: Option<String>,
+        shown: usize,
+    ) -> Result<bool, String> {
+        let config = Config::load()?;
+        let mut format_config = config
+            .formats
+            .iter()
+            .find(|config| config.name == format)
+            .cloned()
+            .unwrap_or_else(|| FormatConfig {
+                name: "pattern".into(),
+                pattern: format.to_owned(),
+                timestamp: None,
+                files: None,
+            });
+        if timestamp.is_some() {
+            format_config.timestamp = timestamp;
+        }
+        let format = LogFormat::Custom(Arc::new(CustomFormat::from_config(&format_config)?));
+
+        let mut log_file = LogFile::new(file_path)?;
+        log_file.set_multiline(Multiline::from_config(&config.multiline)?);
+        log_file.set_format(format.clone());
+        let entries = log_file.get_entries()?;
+
+        let mut matched = 0;
+        let mut timed = 0;
+        let mut has_time = 0;
+        for (index, entry) in entries.iter().enumerate() {
+            let first_line = entry.content.lines().next().unwrap_or_default();
+            let is_match = format.matches(first_line);
+            matched += usize::from(is_match);
+            has_time += usize::from(entry.time().is_some());
+            timed += usize::from(entry.timestamp.is_some());
+            if index >= shown {
+                continue;
+            }
+
+            if !is_match {
+                println!("line {}: no match\n  {first_line}", entry.line + 1);
+                continue;
+            }
+            println!("line {}: matched", entry.line + 1);
+            let width = entry.fields.iter().map(|f| f.name.len()).max().unwrap_or(0);
+            for field in &entry.fields {
+                println!("  {:width$}  {}", field.name, field.value);
+            }
+            if let Some(level) = entry.level {
+                println!("  => level {level}");
+            }
+            match (entry.time(), entry.timestamp) {
+                (_, Some(time)) => println!("  => time {}", time.to_rfc3339()),
+                (Some(time), None) => println!("  => time '{time}' could not be read"),
+                (None, None) => {}
+            }
+        }
+
+        println!(
+            "{matched} of {} entries matched, {timed} of {has_time} times could be read",
+            entries.len()
+        );
+        Ok(matched == entries.len() && timed == has_time)
+    }
+
+    /// Read the entries of the file at `file_path`, grouping lines and detecting the
+    /// format with the settings of the configuration file.
+    fn read_entries(file_path: String, config: &Config) -> Result<Vec<LogEntry>, String> {
+        let mut log_file = LogFile::new(file_path)?;
+        log_file.set_multiline(Multiline::from_config(&config.multiline)?);
+        log_file.set_custom_formats(&CustomFormat::from_configs(&config.formats)?);
+        log_file.get_entries()
+    }
+
+    /// Print the entries of a log file matching the query and written since the time
+    /// of `args`, colored by level when standard output is a terminal. Returns whether
+    /// any entry matched.
+    fn print_entries(args: &PrintArgs) -> Result<bool, String> {
+        let config = Config::load()?;
+        let filter = args
+            .query
+            .as_deref()
+            .map(Self::parse_query)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let since = args
+            .since
+            .as_deref()
+            .map(|since| {
+                timestamp::parse_since(since)
+                    .ok_or_else(|| format!("'{since}' is not a time or an age like 1h"))
+            })
+            .transpose()?;
+        let theme = if io::stdout().is_terminal() && !args.json {
+            Some(Theme::from_config(&config.theme)?)
+        } else {
+            None
+        };
+        let printer = Printer::new(theme, args.json, args.line_number);
+
+        let entries = Self::read_entries(args.file.clone(), &config)?;
+        let mut out = io::BufWriter::new(io::stdout().lock());
+        let mut matched = false;
+        // Entries without a time can't be placed after `since`
+        for entry in entries.iter().filter(|entry| {
+            filter.as_ref().is_none_or(|filter| filter.matches(entry))
+                && since.is_none_or(|since| entry.timestamp.is_some_and(|time| time >= since))
+        }) {
+            matched = true;
+            match printer.print(&mut out, entry) {
+                Ok(()) => {}
+                Err(e) if is_broken_pipe(&e) => return Ok(true),
+                Err(e) => return Err(e.to_string()),
+            }
+        }
+        match out.flush() {
+            Err(e) if !is_broken_pipe(&e) => Err(e.to_string()),
+            _ => Ok(matched),
+        }
+    }
+
+    /// Print the summary of the entries of the file at `file_path` matching `query`,
+    /// listing the `top` most frequent messages, as text or as JSON.
+    fn print_stats(
+        file_path: String,
+        query: Option<&str>,
+        top: usize,
+        json: bool,
+    ) -> Result<(), String> {
+        let filter = query
+            .map(Self::parse_query)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let title = match query {
+            Some(query) => format!("{file_path} ({query})"),
+            None => file_path.clone(),
+        };
+        let entries = Self::read_entries(file_path, &Config::load()?)?;
+        let stats = Stats::collect(
+            entries
+                .iter()
+                .filter(|entry| filter.as_ref().is_none_or(|filter| filter.matches(entry))),
+            top,
+        );
+        if json {
+            println!("{}", stats.json());
+        } else {
+            print!("{}", stats.report(&title));
+        }
+        Ok(())
+    }
+
+    /// Write the entries of the file at `file_path` matching `query` to `output`, or
+    /// to standard output, in `format` or the format of the output's extension. CSV
+    /// files get the `columns`, or the line, time, level and message when none are given.
+    fn export(
+        file_path: String,
+        output: Option<&str>,
+        format: Option<&str>,
+        query: Option<&str>,
+        columns: &[String],
+    ) -> Result<(), String> {
+        let format = match (format, output) {
+            (Some(name), _) => ExportFormat::parse(name).ok_or_else(|| {
+                let names = ExportFormat::NAMES.join(", ");
+                format!("unknown format '{name}', expected one of {names}")
+            })?,
+            (None, Some(output)) => ExportFormat::from_path(output),
+            (None, None) => ExportFormat::Plain,
+        };
+        let filter = query
+            .map(Self::parse_query)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let title = match query {
+            Some(query) => format!("{file_path} ({query})"),
+            None => file_path.clone(),
+        };
+
+        let all_entries = Self::read_entries(file_path, &Config::load()?)?;
+        let entries: Vec<&LogEntry> = all_entries
+            .iter()
+            .filter(|entry| filter.as_ref().is_none_or(|filter| filter.matches(entry)))
+            .collect();
+        let columns = if columns.is_empty() {
+            export::csv_columns(&tui::default_columns(&all_entries), &entries)
+        } else {
+            columns.to_vec()
+        };
+
+        match output {
+            Some(output) => {
+                let file = fs::File::create(output).map_err(|e| format!("{output}: {e}"))?;
+                let mut out = io::BufWriter::new(file);
+                export::write(&mut out, format, &entries, &columns, &title)
+                    .map_err(|e| format!("{output}: {e}"))?;
+                eprintln!("wrote {} entries to {output}", entries.len());
+            }
+            None => export::write(&mut io::stdout().lock(), format, &entries, &columns, &title)
+                .map_err(|e| e.to_string())?,
+        }
+        Ok(())
+    }
+
+    /// Print the last `tail` entries matching `query`, then the new ones as they are
+    /// written until Ctrl-C, colored by level when standard output is a terminal. When
+    /// the file is truncated or replaced, like after log rotation, the new file is
+    /// printed from its start.
+    fn follow_without_tui(file_path: String, query: Option<&str>, tail: usize) -> io::Result<()> {
+        let config = Config::load().map_err(|e| io::Error::other(format!("Config error: {e}")))?;
+        let multiline = Multiline::from_config(&config.multiline)
+            .map_err(|e| io::Error::other(format!("Multiline error: {e}")))?;
+        let formats = CustomFormat::from_configs(&config.formats)
+            .map_err(|e| io::Error::other(format!("Format error: {e}")))?;
+        let theme = if io::stdout().is_terminal() {
+            let theme = Theme::from_config(&config.theme)
+                .map_err(|e| io::Error::other(format!("Theme error: {e}")))?;
+            Some(theme)
+        } else {
+            None
+        };
+        let filter = query.map(Self::parse_query).transpose()?;
+        let printer = Printer::new(theme, false, false);
+
+        let mut log_file = LogFile::new(file_path).map_err(io::Error::other)?;
+        log_file.set_multiline(multiline);
+        log_file.set_custom_formats(&formats);
+
+        // The directory is watched, since a rotated file is replaced by a new one
+        let (sender, receiver) = mpsc::channel();
+        let path = fs::canonicalize(log_file.path())?;
+        let directory = path.parent().unwrap_or(&path).to_owned();
+        let file_events = sender.clone();
+        let watched = path.clone();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            if let Ok(event) = res
+                && event.paths.contains(&watched)
+            {
+                let _ = file_events.send(AppEvent::FileChanged);
+            }
+        })
+        .map_err(|e| io::Error::other(format!("Watcher error: {e}")))?;
+        watcher
+            .watch(&directory, RecursiveMode::NonRecursive)
+            .map_err(|e| io::Error::other(format!("Watch error: {e}")))?;
+        ctrlc::set_handler(move || {
+            let _ = sender.send(AppEvent::Interrupt);
+        })
+        .map_err(|e| io::Error::other(format!("Signal error: {e}")))?;
+
+        let matches = |entry: &LogEntry| filter.as_ref().is_none_or(|filter| filter.matches(entry));
+        let mut out = io::stdout().lock();
+        // The first line, number of lines and whether it was printed, of the last entry
+        let mut last: Option<(usize, usize, bool)> = None;
+
+        let mut entries = log_file.get_entries().map_err(io::Error::other)?;
+        if log_file.ends_with_partial_line() {
+            entries.pop();
+        }
+        let first = entries.iter().filter(|entry| matches(entry)).count();
+        let mut printed = 0;
+        for entry in &entries {
+            let shown = matches(entry) && {
+                printed += 1;
+                printed + tail > first
+            };
+            if shown {
+                printer.print(&mut out, entry)?;
+            }
+            last = Some((entry.line, entry.line_count(), shown));
+        }
+        out.flush()?;
+
+        while let Ok(AppEvent::FileChanged) = receiver.recv() {
+            // Read once for a burst of changes
+            if receiver
+                .try_iter()
+                .any(|event| matches!(event, AppEvent::Interrupt))
+            {
+                break;
+            }
+
+            if log_file.reset_if_rotated().map_err(io::Error::other)? {
+                eprintln!(
+                    "logz: {} was replaced, following the new file",
+                    log_file.path()
+                );
+                last = None;
+            }
+            let mut entries = match log_file.get_entries() {
+                Ok(entries) => entries,
+                // The file is gone for a moment while it is rotated
+                Err(_) if !path.exists() => continue,
+                Err(e) => return Err(io::Error::other(e)),
+            };
+            // A line still being written is printed once it is complete
+            if log_file.ends_with_partial_line() {
+                entries.pop();
+            }
+            for entry in &entries {
+                let count = entry.line_count();
+                match last {
+                    // Lines were added to the last entry, like the rest of a stack trace
+                    Some((line, lines, true)) if line == entry.line => {
+                        printer.print_rest(&mut out, entry, lines)?;
+                        last = Some((line, count, true));
+                        continue;
+                    }
+                    Some((line, _, false)) if line == entry.line && !matches(entry) => {
+                        last = Some((line, count, false));
+                        continue;
+                    }
+                    _ => {}
+                }
+                let shown = matches(entry);
+                if shown {
+                    printer.print(&mut out, entry)?;
+                }
+                last = Some((entry.line, count, shown));
+            }
+            out.flush()?;
+        }
+        Ok(())
     }
 
-    /// run the application in single-file mode with TUI
-    pub fn run_single_file_with_tui(file_path: String) -> io::Result<()> {
+    /// run the application in single-file mode with TUI, showing the entries matching
+    /// `query` when there is one
+    pub fn run_single_file_with_tui(file_path: String, query: Option<&str>) -> io::Result<()> {
+        // Load the keymap and theme before taking over the terminal so config errors stay readable
+        let config = Config::load().map_err(|e| io::Error::other(format!("Config error: {e}")))?;
+        let keymap = KeyMap::from_config(&config.keys)
+            .map_err(|e| io::Error::other(format!("Keymap error: {e}")))?;
+        let theme = Theme::from_config(&config.theme)
+            .map_err(|e| io::Error::other(format!("Theme error: {e}")))?;
+        let clipboard = Clipboard::from_config(&config.clipboard)
+            .map_err(|e| io::Error::other(format!("Clipboard error: {e}")))?;
+        let multiline = Multiline::from_config(&config.multiline)
+            .map_err(|e| io::Error::other(format!("Multiline error: {e}")))?;
+        let formats = CustomFormat::from_configs(&config.formats)
+            .map_err(|e| io::Error::other(format!("Format error: {e}")))?;
+        let error_level = LogLevel::parse(&config.display.error_level).ok_or_else(|| {
+            let level = &config.display.error_level;
+            io::Error::other(format!("Config error: unknown error_level '{level}'"))
+        })?;
+        let filter = query.map(Self::parse_query).transpose()?;
+
         // Initialize TUI
-        let mut tui = Tui::new()?;
+        let mut tui = Tui::new(keymap, theme)?;
+        tui.set_error_level(error_level);
+        tui.set_clipboard(clipboard);
+        tui.set_filter(filter);
+        tui.set_ansi(config.display.ansi);
         tui.start()?;
 
         // Ensure we clean up the terminal even if there's an error
-        let result = Self::run_tui_loop(file_path, &mut tui);
+        let result = Self::run_tui_loop(file_path, multiline, &formats, &mut tui);
 
         // Always try to end the TUI cleanly
         let _ = tui.end();
@@ -62,8 +549,22 @@ impl LogViewer {
         result
     }
 
+    /// Parse the `--query` of the command line, pointing at where it went wrong when
+    /// it can't be parsed.
+    fn parse_query(query: &str) -> io::Result<Filter> {
+        Filter::parse(query).map_err(|e| {
+            let marker = " ".repeat(e.column - 1);
+            io::Error::other(format!("Query error: {e}\n  {query}\n  {marker}^"))
+        })
+    }
+
     /// Main TUI loop with file watching
-    fn run_tui_loop(file_path: String, tui: &mut Tui) -> io::Result<()> {
+    fn run_tui_loop(
+        file_path: String,
+        multiline: Option<Multiline>,
+        formats: &[Arc<CustomFormat>],
+        tui: &mut Tui,
+    ) -> io::Result<()> {
         let mut log_file = match LogFile::new(file_path) {
             Ok(file) => file,
             Err(err) => {
@@ -71,34 +572,135 @@ impl LogViewer {
                 return Err(std::io::Error::other(err));
             }
         };
+        log_file.set_multiline(multiline.clone());
+        log_file.set_custom_formats(formats);
 
         // Load initial log entries
+        tui.set_file_name(log_file.path().to_owned());
         Self::load_initial_log_entries(&mut log_file, tui)?;
 
+        // Keyboard, resize, timer and file change events all arrive on one channel
+        let events = EventLoop::new(TICK_RATE);
+
         // Set up file watcher
-        let (tx, rx) = channel();
-        let mut watcher = notify::recommended_watcher(tx)
-            .map_err(|e| io::Error::other(format!("Watcher error: {e}")))?;
+        let file_events = events.sender();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            if let Ok(Event {
+                kind: EventKind::Modify(_),
+                ..
+            }) = res
+            {
+                let _ = file_events.send(AppEvent::FileChanged);
+            }
+        })
+        .map_err(|e| io::Error::other(format!("Watcher error: {e}")))?;
 
         let path: PathBuf = log_file.clone().into();
         watcher
             .watch(path.as_path(), RecursiveMode::NonRecursive)
             .map_err(|e| io::Error::other(format!("Watch error: {e}")))?;
 
-        // Use the TUI's main loop with file watching as external event handler
-        tui.run_loop(|tui_ref| {
-            // Check for file changes (non-blocking)
-            if let Ok(Ok(Event {
-                kind: EventKind::Modify(_),
-                ..
-            })) = rx.try_recv()
-            {
-                Self::update_log_entries_tui(&mut log_file, tui_ref)?;
+        // Use the TUI's main loop, reading the file once per batch of change notifications
+        tui.run_loop(&events, |tui_ref, request| {
+            match request {
+                TuiRequest::Reload => Self::update_log_entries_tui(&mut log_file, tui_ref)?,
+                TuiRequest::SaveColumns(columns) => {
+                    Self::save_columns(&log_file, columns, tui_ref);
+                }
+                TuiRequest::SaveBookmarks(bookmarks, marks) => {
+                    Self::save_bookmarks(&log_file, &bookmarks, &marks, tui_ref);
+                }
+                TuiRequest::SaveNotes(notes) => Self::save_notes(&log_file, &notes, tui_ref),
+                TuiRequest::Edit(line) => Self::edit(&log_file, line, &events, tui_ref)?,
+                TuiRequest::Open(file_path) => {
+                    Self::open_log_file(
+                        file_path,
+                        multiline.clone(),
+                        formats,
+                        &mut log_file,
+                        &mut watcher,
+                        tui_ref,
+                    )?;
+                }
             }
             Ok(true) // Continue running
         })
     }
 
+    /// Open the log file in `$VISUAL` or `$EDITOR` at `line`, counting from 0, with the
+    /// TUI suspended until the editor exits. The view is left as it was.
+    fn edit(log_file: &LogFile, line: usize, events: &EventLoop, tui: &mut Tui) -> io::Result<()> {
+        let editor = env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .unwrap_or_else(|_| "vi".to_owned());
+
+        events.pause_input();
+        tui.end()?;
+        // The shell splits editors with arguments, like `code --wait`
+        let status = process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{editor} \"$@\""))
+            .arg("sh")
+            .arg(format!("+{}", line + 1))
+            .arg(log_file.path())
+            .status();
+        tui.start()?;
+        events.resume_input();
+
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => tui.show_error(format!("{editor}: {status}")),
+            Err(e) => tui.show_error(format!("{editor}: {e}")),
+        }
+        Ok(())
+    }
+
+    /// Switch the TUI over to another log file, watching it instead of the current one.
+    /// Problems with the new file are shown in the TUI and keep the current file open.
+    fn open_log_file(
+        file_path: String,
+        multiline: Option<Multiline>,
+        formats: &[Arc<CustomFormat>],
+        log_file: &mut LogFile,
+        watcher: &mut impl Watcher,
+        tui: &mut Tui,
+    ) -> io::Result<()> {
+        let mut new_file = match LogFile::new(file_path.clone()) {
+            Ok(file) => file,
+            Err(err) => {
+                tui.show_error(format!("{file_path}: {err}"));
+                return Ok(());
+            }
+        };
+        new_file.set_multiline(multiline);
+        new_file.set_custom_formats(formats);
+
+        let entries = match new_file.get_entries() {
+            Ok(entries) => entries,
+            Err(err) => {
+                tui.show_error(format!("{file_path}: {err}"));
+                return Ok(());
+            }
+        };
+
+        let old_path: PathBuf = log_file.clone().into();
+        let new_path: PathBuf = new_file.clone().into();
+        let _ = watcher.unwatch(old_path.as_path());
+        watcher
+            .watch(new_path.as_path(), RecursiveMode::NonRecursive)
+            .map_err(|e| io::Error::other(format!("Watch error: {e}")))?;
+
+        *log_file = new_file;
+        tui.set_file_name(log_file.path().to_owned());
+        tui.set_log_entries(entries);
+        tui.set_format_name("");
+        Self::show_format(log_file, tui);
+        Self::load_columns(log_file, tui);
+        Self::load_file_record(log_file, tui);
+        tui.show_info(format!("opened {}", log_file.path()));
+        Ok(())
+    }
+
     /// Load initial log entries into the TUI
     fn load_initial_log_entries(log_file: &mut LogFile, tui: &mut Tui) -> io::Result<()> {
         let entries: Vec<LogEntry> = match log_file.get_entries() {
@@ -111,6 +713,9 @@ impl LogViewer {
 
         // Set initial entries (don't auto-scroll to bottom on initial load)
         tui.set_log_entries(entries);
+        Self::show_format(log_file, tui);
+        Self::load_columns(log_file, tui);
+        Self::load_file_record(log_file, tui);
         Ok(())
     }
 
@@ -126,6 +731,130 @@ impl LogViewer {
 
         // Only add new entries (this will auto-scroll to show new entries)
         tui.append_new_log_entries(entries);
+        Self::show_format(log_file, tui);
         Ok(())
     }
+
+    /// Set the table columns saved for the application the log file belongs to. Files
+    /// outside of registered applications start with the default columns.
+    fn load_columns(log_file: &LogFile, tui: &mut Tui) {
+        let registry = match Registry::load() {
+            Ok(registry) => registry,
+            Err(e) => {
+                tui.show_error(format!("registry: {e}"));
+                return;
+            }
+        };
+        let columns = registry
+            .application_for(log_file.path())
+            .and_then(|name| registry.applications.get(name))
+            .map(|application| application.columns.clone())
+            .unwrap_or_default();
+        tui.set_columns(columns);
+    }
+
+    /// Save the table columns for the application the log file belongs to. Columns
+    /// of files outside of registered applications are only kept while they are open.
+    fn save_columns(log_file: &LogFile, columns: Vec<String>, tui: &mut Tui) {
+        let result = Registry::load().and_then(|mut registry| {
+            let Some(name) = registry.application_for(log_file.path()).map(str::to_owned) else {
+                return Ok(None);
+            };
+            if let Some(application) = registry.applications.get_mut(&name)
+                && application.columns != columns
+            {
+                application.columns = columns;
+                registry.save()?;
+                return Ok(Some(name));
+            }
+            Ok(None)
+        });
+        match result {
+            Ok(Some(name)) => tui.show_info(format!("saved the columns of {name}")),
+            Ok(None) => {}
+            Err(e) => tui.show_error(format!("registry: {e}")),
+        }
+    }
+
+    /// Set the bookmarks, marks and notes saved for the log file. The registry counts
+    /// lines from 1, the TUI from 0.
+    fn load_file_record(log_file: &LogFile, tui: &mut Tui) {
+        let registry = match Registry::load() {
+            Ok(registry) => registry,
+            Err(e) => {
+                tui.show_error(format!("registry: {e}"));
+                return;
+            }
+        };
+        let record = registry.file(log_file.path()).cloned().unwrap_or_default();
+        tui.set_bookmarks(
+            record
+                .bookmarks
+                .iter()
+                .map(|line| line.saturating_sub(1))
+                .collect(),
+            record
+                .marks
+                .iter()
+                .map(|(&name, line)| (name, line.saturating_sub(1)))
+                .collect(),
+        );
+        tui.set_notes(
+            record
+                .notes
+                .into_iter()
+                .map(|note| (note.line.saturating_sub(1), note.text))
+                .collect(),
+        );
+    }
+
+    /// Save the bookmarks and marks of the log file.
+    fn save_bookmarks(
+        log_file: &LogFile,
+        bookmarks: &BTreeSet<usize>,
+        marks: &BTreeMap<char, usize>,
+        tui: &mut Tui,
+    ) {
+        Self::update_file_record(log_file, tui, |record| {
+            record.bookmarks = bookmarks.iter().map(|line| line + 1).collect();
+            record.marks = marks.iter().map(|(&name, line)| (name, line + 1)).collect();
+        });
+    }
+
+    /// Save the notes of the log file.
+    fn save_notes(log_file: &LogFile, notes: &BTreeMap<usize, String>, tui: &mut Tui) {
+        Self::update_file_record(log_file, tui, |record| {
+            record.notes = notes
+                .iter()
+                .map(|(line, text)| Note {
+                    line: line + 1,
+                    text: text.clone(),
+                })
+                .collect();
+        });
+    }
+
+    /// Change the record of the log file in the registry with `update`, and save it.
+    fn update_file_record(log_file: &LogFile, tui: &mut Tui, update: impl FnOnce(&mut FileRecord)) {
+        let result = Registry::load().and_then(|mut registry| {
+            registry.update_file(log_file.path(), update)?;
+            registry.save()
+        });
+        if let Err(e) = result {
+            tui.show_error(format!("registry: {e}"));
+        }
+    }
+
+    /// Show the format of the log file in the TUI once it has been detected.
+    fn show_format(log_file: &LogFile, tui: &mut Tui) {
+        if let Some(format) = log_file.format() {
+            tui.set_format_name(format.name());
+        }
+    }
+}
+
+/// Check if writing to standard output failed because the reader went away, like
+/// `head` after enough lines, which ends the output without being an error.
+fn is_broken_pipe(error: &io::Error) -> bool {
+    error.kind() == io::ErrorKind::BrokenPipe
 }
@@ -12,10 +12,20 @@
 // unicode-truncate (which depends on another version of unicode-width)
 #![allow(clippy::multiple_crate_versions)]
 
+mod ansi;
+mod config;
+mod event;
+mod export;
+mod filter;
+mod format;
 mod log_entry;
 mod log_file;
 mod log_viewer;
+mod multiline;
 mod persistence;
+mod printer;
+mod stats;
+mod timestamp;
 mod tui;
 
 use clap::{Parser, Subcommand};
@@ -49,8 +59,19 @@ struct Args {
     /// Follow mode to auto-scroll to new content
     #[arg(short, long, default_value = "false")]
     follow: bool,
+    /// Only show the entries matching a query, like 'level>=warn and not msg~health'
+    #[arg(short, long)]
+    query: Option<String>,
+    /// Print new entries as they are written instead of opening the TUI, like `tail -f`.
+    /// This is the default when standard output is not a terminal
+    #[arg(long)]
+    no_tui: bool,
+    /// How many of the last entries to print before following without the TUI
+    #[arg(short = 'n', long, default_value = "10")]
+    lines: usize,
 }
 
+/// A subcommand
 #[derive(Subcommand, Debug, Clone)]
 enum Commands {
     /// Manage registered applications
@@ -59,6 +80,85 @@ enum Commands {
         #[command(subcommand)]
         action: ApplicationAction,
     },
+    /// Print the entries of a log file, or the ones matching a query
+    Cat(PrintArgs),
+    /// Print the entries of a log file matching a query, exiting with 1 when none match
+    Grep(PrintArgs),
+    /// Summarize a log file: entries per level, first and last time, busiest minute
+    /// and the most frequent messages
+    Stats {
+        /// The log file to summarize
+        file: String,
+        /// Only count the entries matching a query, like 'service=api'
+        #[arg(short, long)]
+        query: Option<String>,
+        /// How many of the most frequent messages to list
+        #[arg(short = 'n', long, default_value = "10")]
+        top: usize,
+        /// Print the summary as a JSON object
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Write the entries of a log file, or the ones matching a query, to a file
+    Export {
+        /// The log file to export
+        file: String,
+        /// The file to write, or standard output when not given
+        #[arg(short, long)]
+        output: Option<String>,
+        /// text, jsonl, csv or html; taken from the output file's extension when not given
+        #[arg(short, long)]
+        format: Option<String>,
+        /// Only export the entries matching a query, like 'level>=warn'
+        #[arg(short, long)]
+        query: Option<String>,
+        /// The fields written as CSV columns, like 'time,level,msg'
+        #[arg(short, long, value_delimiter = ',')]
+        columns: Vec<String>,
+    },
+    /// Work with the line formats of the configuration file
+    Format {
+        /// The action to perform
+        #[command(subcommand)]
+        action: FormatAction,
+    },
+}
+
+/// The arguments of the commands printing entries
+#[derive(clap::Args, Debug, Clone)]
+struct PrintArgs {
+    /// The log file to read
+    file: String,
+    /// Only print the entries matching a query, like 'level>=error'
+    #[arg(short, long)]
+    query: Option<String>,
+    /// Only print the entries written since a time, or in the last 30s, 15m, 1h, 2d or 1w
+    #[arg(short, long)]
+    since: Option<String>,
+    /// Print each entry as a JSON object with its line, time, level and fields
+    #[arg(short, long)]
+    json: bool,
+    /// Put the line number in front of each entry
+    #[arg(short = 'n', long)]
+    line_number: bool,
+}
+
+/// A format action
+#[derive(Subcommand, Debug, Clone)]
+enum FormatAction {
+    /// Check a format against the entries of a sample file
+    Test {
+        /// The name of a format from the configuration file, or a regex with named groups
+        format: String,
+        /// The sample log file
+        file: String,
+        /// The strftime-style format of the time field, like "%Y-%m-%d %H:%M:%S"
+        #[arg(short, long)]
+        timestamp: Option<String>,
+        /// How many entries to show the fields of
+        #[arg(short = 'n', long, default_value = "10")]
+        entries: usize,
+    },
 }
 
 /// An application action
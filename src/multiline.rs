@@ -0,0 +1,75 @@
+//! `multiline` decides which lines of a log file belong together in one `LogEntry`,
+//! like the lines of a stack trace.
+
+use crate::{ansi, config::MultilineConfig};
+use regex::Regex;
+
+/// `DEFAULT_CONTINUATIONS` match the lines of Java, Python and Rust stack traces.
+const DEFAULT_CONTINUATIONS: [&str; 8] = [
+    r"^\s+\S",
+    r"^at ",
+    r"^([a-zA-Z_$][\w$]*\.)+[\w$]*(Exception|Error|Throwable)\b",
+    r"^[A-Z]\w*(Error|Exception)(:|$)",
+    r"^Caused by:",
+    r"^\.\.\. \d+ (more|common frames omitted)",
+    r"^Traceback \(most recent call last\):",
+    r"^stack backtrace:",
+];
+
+/// `Multiline` holds the rules that fold continuation lines into the entry before them.
+#[derive(Debug, Clone)]
+pub struct Multiline {
+    /// `continuation` are patterns matching lines that continue the previous entry.
+    continuation: Vec<Regex>,
+    /// `start` matches the first line of an entry. When set, it decides on its own.
+    start: Option<Regex>,
+}
+
+impl Multiline {
+    /// Build the rules from the `[multiline]` section of the configuration file.
+    /// Returns `None` when grouping is turned off.
+    pub fn from_config(config: &MultilineConfig) -> Result<Option<Self>, String> {
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        let compile = |pattern: &str| {
+            Regex::new(pattern).map_err(|e| format!("invalid pattern '{pattern}': {e}"))
+        };
+        let continuation = match &config.continuation {
+            Some(patterns) => patterns
+                .iter()
+                .map(|pattern| compile(pattern))
+                .collect::<Result<_, _>>()?,
+            None => DEFAULT_CONTINUATIONS
+                .into_iter()
+                .map(compile)
+                .collect::<Result<_, _>>()?,
+        };
+        let start = config.start.as_deref().map(compile).transpose()?;
+
+        Ok(Some(Self {
+            continuation,
+            start,
+        }))
+    }
+
+    /// Check if `line` continues the entry before it.
+    pub fn is_continuation(&self, line: &str) -> bool {
+        let stripped;
+        let line = if ansi::has_escapes(line) {
+            stripped = ansi::strip(line);
+            stripped.as_str()
+        } else {
+            line
+        };
+
+        match &self.start {
+            Some(start) => !start.is_match(line),
+            None => self
+                .continuation
+                .iter()
+                .any(|pattern| pattern.is_match(line)),
+        }
+    }
+}
@@ -1,4 +1,190 @@
 //! `persistence` is the module that deals with saving application
 //! settings like Application names and directories.
 
-// TODO: Create a persistence structure of some kind. Could be TOML/YAML/JSON/Sqlite
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// `REGISTRY_FILE_NAME` is the name of the registry file inside the logz data directory.
+const REGISTRY_FILE_NAME: &str = "registry.toml";
+
+/// `Registry` is everything logz remembers between runs, saved as TOML in the data
+/// directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Registry {
+    /// `applications` maps the names of the registered applications onto their settings.
+    pub applications: BTreeMap<String, Application>,
+    /// `files` maps the canonical paths of log files onto what is remembered about them.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub files: BTreeMap<String, FileRecord>,
+}
+
+/// `Application` is a registered application with the directory it logs to.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Application {
+    /// `directory` is the absolute path of the root logging directory.
+    pub directory: String,
+    /// `columns` are the fields shown as columns in the table view of the
+    /// application's log files.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<String>,
+}
+
+/// `FileRecord` is what logz remembers about a single log file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileRecord {
+    /// `inode` identifies the file on disk, so that a new file at the same path, like
+    /// after log rotation, doesn't get the record of the old one.
+    pub inode: u64,
+    /// `bookmarks` are the first lines of the bookmarked entries, counting from 1.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub bookmarks: BTreeSet<usize>,
+    /// `marks` maps the letters of the marks onto the first line of the entry each is
+    /// set on, counting from 1.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub marks: BTreeMap<char, usize>,
+    /// `notes` are the notes written on entries, in line order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+}
+
+impl FileRecord {
+    /// Check if there is nothing to remember about the file.
+    fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty() && self.marks.is_empty() && self.notes.is_empty()
+    }
+}
+
+/// `Note` is free text written on an entry of a log file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Note {
+    /// `line` is the first line of the entry, counting from 1.
+    pub line: usize,
+    /// `text` is the note itself.
+    pub text: String,
+}
+
+impl Registry {
+    /// `path` is the location of the registry file, usually
+    /// `~/.local/share/logz/registry.toml`.
+    pub fn path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("logz").join(REGISTRY_FILE_NAME))
+    }
+
+    /// Load the registry, which is empty when the file does not exist yet.
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = Self::path().filter(|path| path.is_file()) else {
+            return Ok(Self::default());
+        };
+
+        let contents = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        toml::from_str(&contents).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Save the registry, replacing the file in one step so that it is never left
+    /// half written.
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("no data directory to save the registry in")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        }
+
+        let contents = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let temporary = path.with_extension("toml.tmp");
+        fs::write(&temporary, contents).map_err(|e| format!("{}: {e}", temporary.display()))?;
+        fs::rename(&temporary, &path).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    /// Register the application `name` logging to `directory`.
+    pub fn add(&mut self, name: &str, directory: &str) -> Result<&Application, String> {
+        if self.applications.contains_key(name) {
+            return Err(format!("application '{name}' is already registered"));
+        }
+
+        let expanded = shellexpand::full(directory).map_err(|e| e.to_string())?;
+        let directory =
+            fs::canonicalize(expanded.as_ref()).map_err(|e| format!("{directory}: {e}"))?;
+        if !directory.is_dir() {
+            return Err(format!("{}: not a directory", directory.display()));
+        }
+
+        Ok(self
+            .applications
+            .entry(name.to_owned())
+            .or_insert(Application {
+                directory: directory.to_string_lossy().into_owned(),
+                columns: Vec::new(),
+            }))
+    }
+
+    /// Unregister the application `name`.
+    pub fn remove(&mut self, name: &str) -> Result<Application, String> {
+        self.applications
+            .remove(name)
+            .ok_or_else(|| format!("no application named '{name}'"))
+    }
+
+    /// The name of the application whose directory holds the file at `path`. When
+    /// directories are nested, the deepest one wins.
+    pub fn application_for(&self, path: &str) -> Option<&str> {
+        let path = fs::canonicalize(path).ok()?;
+        self.applications
+            .iter()
+            .filter(|(_, application)| path.starts_with(Path::new(&application.directory)))
+            .max_by_key(|(_, application)| application.directory.len())
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// The record of the log file at `path`, unless it was saved for an earlier file
+    /// at the same path.
+    pub fn file(&self, path: &str) -> Option<&FileRecord> {
+        let (key, inode) = file_key(path).ok()?;
+        self.files.get(&key).filter(|record| record.inode == inode)
+    }
+
+    /// Change the record of the log file at `path` with `update`. A record saved for
+    /// an earlier file at the same path starts over, and empty records are dropped.
+    pub fn update_file(
+        &mut self,
+        path: &str,
+        update: impl FnOnce(&mut FileRecord),
+    ) -> Result<(), String> {
+        let (key, inode) = file_key(path)?;
+        let mut record = self
+            .files
+            .remove(&key)
+            .filter(|record| record.inode == inode)
+            .unwrap_or_default();
+        record.inode = inode;
+        update(&mut record);
+        if !record.is_empty() {
+            self.files.insert(key, record);
+        }
+        Ok(())
+    }
+}
+
+/// The canonical path of the file at `path`, with its inode.
+fn file_key(path: &str) -> Result<(String, u64), String> {
+    let path = fs::canonicalize(path).map_err(|e| format!("{path}: {e}"))?;
+    let metadata = fs::metadata(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok((path.to_string_lossy().into_owned(), inode(&metadata)))
+}
+
+/// The inode of a file, which stays the same when the file is renamed.
+#[cfg(unix)]
+pub fn inode(metadata: &fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::ino(metadata)
+}
+
+/// The inode of a file, which platforms without inodes don't have.
+#[cfg(not(unix))]
+pub const fn inode(_metadata: &fs::Metadata) -> u64 {
+    0
+}
@@ -0,0 +1,116 @@
+//! `printer` writes log entries to standard output for the commands that don't use
+//! the TUI, like `logz cat` and `logz grep`.
+
+use crate::{export, log_entry::LogEntry, tui::Theme};
+// The crossterm of ratatui converts its colors
+use ratatui::{
+    crossterm::style::{Attribute, SetAttribute, SetBackgroundColor, SetForegroundColor},
+    style::{Color, Modifier, Style},
+};
+use std::{fmt::Write as _, io};
+
+/// `RESET` turns every color and attribute back off.
+const RESET: &str = "\x1b[0m";
+
+/// `MODIFIER_ATTRIBUTES` are the text attributes written for each style modifier.
+const MODIFIER_ATTRIBUTES: [(Modifier, Attribute); 5] = [
+    (Modifier::BOLD, Attribute::Bold),
+    (Modifier::DIM, Attribute::Dim),
+    (Modifier::ITALIC, Attribute::Italic),
+    (Modifier::UNDERLINED, Attribute::Underlined),
+    (Modifier::REVERSED, Attribute::Reverse),
+];
+
+/// `Printer` writes entries one after another, as text colored by level or as JSON.
+#[derive(Debug, Clone)]
+pub struct Printer {
+    /// `theme` colors the entries by level, or `None` to write plain text.
+    theme: Option<Theme>,
+    /// `json` writes each entry as a JSON object on its own line.
+    json: bool,
+    /// `line_numbers` puts the line number in front of each entry.
+    line_numbers: bool,
+}
+
+impl Printer {
+    /// Create a `Printer`, coloring entries with `theme` when there is one.
+    pub const fn new(theme: Option<Theme>, json: bool, line_numbers: bool) -> Self {
+        Self {
+            theme,
+            json,
+            line_numbers,
+        }
+    }
+
+    /// Write `entry` to `out`, followed by a newline.
+    pub fn print(&self, out: &mut impl io::Write, entry: &LogEntry) -> io::Result<()> {
+        if self.json {
+            return writeln!(out, "{}", export::json(entry));
+        }
+        if self.line_numbers {
+            let line = (entry.line + 1).to_string();
+            match &self.theme {
+                Some(theme) => write!(out, "{}:", paint(theme.line_number, &line))?,
+                None => write!(out, "{line}:")?,
+            }
+        }
+        self.print_lines(out, entry, 0)
+    }
+
+    /// Write the lines of `entry` after the first `printed` ones to `out`, for an
+    /// entry that grew since it was printed, like a stack trace still being written.
+    pub fn print_rest(
+        &self,
+        out: &mut impl io::Write,
+        entry: &LogEntry,
+        printed: usize,
+    ) -> io::Result<()> {
+        if self.json || printed >= entry.line_count() {
+            return Ok(());
+        }
+        self.print_lines(out, entry, printed)
+    }
+
+    /// Write the lines of `entry` after the first `skipped` ones to `out`, colored by
+    /// its level.
+    fn print_lines(
+        &self,
+        out: &mut impl io::Write,
+        entry: &LogEntry,
+        skipped: usize,
+    ) -> io::Result<()> {
+        let Some(theme) = &self.theme else {
+            let lines: Vec<&str> = entry.content.lines().skip(skipped).collect();
+            return writeln!(out, "{}", lines.join("\n"));
+        };
+        // Colored program output keeps its own colors
+        let text = entry.raw.as_deref().unwrap_or(&entry.content);
+        let lines: Vec<&str> = text.lines().skip(skipped).collect();
+        let text = lines.join("\n");
+        match (&entry.raw, entry.level) {
+            (Some(_), _) => writeln!(out, "{text}{RESET}"),
+            (None, Some(level)) => writeln!(out, "{}", paint(theme.level(level), &text)),
+            (None, None) => writeln!(out, "{text}"),
+        }
+    }
+}
+
+/// `text` wrapped in the escape sequences showing it in `style`.
+fn paint(style: Style, text: &str) -> String {
+    let mut painted = String::new();
+    if let Some(color) = style.fg.filter(|&color| color != Color::Reset) {
+        let _ = write!(painted, "{}", SetForegroundColor(color.into()));
+    }
+    if let Some(color) = style.bg.filter(|&color| color != Color::Reset) {
+        let _ = write!(painted, "{}", SetBackgroundColor(color.into()));
+    }
+    for (modifier, attribute) in MODIFIER_ATTRIBUTES {
+        if style.add_modifier.contains(modifier) {
+            let _ = write!(painted, "{}", SetAttribute(attribute));
+        }
+    }
+    if painted.is_empty() {
+        return text.to_owned();
+    }
+    format!("{painted}{text}{RESET}")
+}
@@ -0,0 +1,218 @@
+//! `stats` summarizes log entries for quick health checks: how many there are of
+//! each level, when they were written and which messages come up the most.
+
+use crate::{
+    log_entry::{LogEntry, LogLevel},
+    timestamp::{self, Timestamp},
+};
+use chrono::Timelike;
+use regex::{Captures, Regex};
+use serde_json::{Map, Value, json};
+use std::{collections::HashMap, fmt::Write, sync::LazyLock};
+
+/// `VARIABLE` matches the parts of a message that change between otherwise equal
+/// messages: UUIDs, hexadecimal IDs of at least 8 digits and numbers.
+static VARIABLE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(?P<uuid>\b[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}\b)",
+        r"|(?P<id>\b(?:0x)?[0-9a-fA-F]{8,}\b)",
+        r"|(?P<number>\d+(?:\.\d+)?)",
+    ))
+    .expect("variable part pattern is valid")
+});
+
+/// `TIME_FORMAT` is how times are written in the report.
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S %:z";
+
+/// `LABEL_WIDTH` is the width of the labels in front of the values of the report.
+const LABEL_WIDTH: usize = 9;
+
+/// Replace the parts of `message` that change between otherwise equal messages with
+/// placeholders: `<uuid>` for UUIDs, `<id>` for hexadecimal IDs and `<n>` for numbers,
+/// so that `user 42 took 0.5s` and `user 7 took 1.25s` are the same message.
+pub fn normalize(message: &str) -> String {
+    VARIABLE
+        .replace_all(message, |captures: &Captures| {
+            if captures.name("uuid").is_some() {
+                "<uuid>".to_owned()
+            } else if let Some(id) = captures.name("id") {
+                // Words like `deadbeef` are made of hex digits too, but aren't IDs
+                if id.as_str().chars().any(|c| c.is_ascii_digit()) {
+                    "<id>".to_owned()
+                } else {
+                    id.as_str().to_owned()
+                }
+            } else {
+                "<n>".to_owned()
+            }
+        })
+        .into_owned()
+}
+
+/// `Stats` is the summary of a set of entries.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// `entries` is the number of entries.
+    pub entries: usize,
+    /// `levels` are the number of entries of each level, in `LogLevel::ALL` order.
+    pub levels: [usize; 6],
+    /// `unleveled` is the number of entries without a level.
+    pub unleveled: usize,
+    /// `first` is the earliest time of an entry.
+    pub first: Option<Timestamp>,
+    /// `last` is the latest time of an entry.
+    pub last: Option<Timestamp>,
+    /// `busiest_minute` is the start of the minute with the most entries, and how many
+    /// there are.
+    pub busiest_minute: Option<(Timestamp, usize)>,
+    /// `top_messages` are the most frequent normalized messages with their count.
+    pub top_messages: Vec<(String, usize)>,
+    /// `top_errors` are the most frequent normalized messages of errors with their count.
+    pub top_errors: Vec<(String, usize)>,
+}
+
+impl Stats {
+    /// Summarize `entries`, keeping the `top` most frequent messages.
+    pub fn collect<'a>(entries: impl IntoIterator<Item = &'a LogEntry>, top: usize) -> Self {
+        let mut stats = Self::default();
+        let mut minutes: HashMap<Timestamp, usize> = HashMap::new();
+        let mut messages: HashMap<String, usize> = HashMap::new();
+        let mut errors: HashMap<String, usize> = HashMap::new();
+
+        for entry in entries {
+            stats.entries += 1;
+            match entry.level {
+                Some(level) => stats.levels[level as usize] += 1,
+                None => stats.unleveled += 1,
+            }
+
+            if let Some(time) = entry.timestamp {
+                stats.first = Some(stats.first.map_or(time, |first| first.min(time)));
+                stats.last = Some(stats.last.map_or(time, |last| last.max(time)));
+                if let Some(minute) = time.with_second(0).and_then(|t| t.with_nanosecond(0)) {
+                    *minutes.entry(minute).or_default() += 1;
+                }
+            }
+
+            let message = normalize(message(entry).trim());
+            if entry.level >= Some(LogLevel::Error) {
+                *errors.entry(message.clone()).or_default() += 1;
+            }
+            *messages.entry(message).or_default() += 1;
+        }
+
+        // The earliest of the busiest minutes, so the result doesn't depend on hashing
+        stats.busiest_minute = minutes
+            .into_iter()
+            .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)));
+        stats.top_messages = most_frequent(messages, top);
+        stats.top_errors = most_frequent(errors, top);
+        stats
+    }
+
+    /// The summary as text, with `title` on top.
+    pub fn report(&self, title: &str) -> String {
+        let mut report = format!("{title}\n\n");
+        let mut row = |label: &str, value: String| {
+            let _ = writeln!(report, "{label:<LABEL_WIDTH$}{value}");
+        };
+        row("Entries", self.entries.to_string());
+
+        let mut levels: Vec<String> = LogLevel::ALL
+            .into_iter()
+            .zip(self.levels)
+            .filter(|&(_, count)| count > 0)
+            .map(|(level, count)| format!("{} {count}", level.to_string().to_lowercase()))
+            .collect();
+        if self.unleveled > 0 {
+            levels.push(format!("no level {}", self.unleveled));
+        }
+        row("Levels", levels.join(", "));
+
+        if let (Some(first), Some(last)) = (self.first, self.last) {
+            row("First", first.format(TIME_FORMAT).to_string());
+            row("Last", last.format(TIME_FORMAT).to_string());
+        }
+        if let Some((minute, count)) = self.busiest_minute {
+            let noun = if count == 1 { "entry" } else { "entries" };
+            row(
+                "Busiest",
+                format!("{} ({count} {noun})", minute.format("%Y-%m-%d %H:%M %:z")),
+            );
+        }
+
+        for (heading, messages) in [
+            ("Top messages", &self.top_messages),
+            ("Top errors", &self.top_errors),
+        ] {
+            if messages.is_empty() {
+                continue;
+            }
+            let width = messages[0].1.to_string().len();
+            let _ = write!(report, "\n{heading}\n");
+            for (message, count) in messages {
+                let _ = writeln!(report, "  {count:>width$}  {message}");
+            }
+        }
+        report
+    }
+
+    /// The summary as a JSON object.
+    pub fn json(&self) -> Value {
+        let levels: Map<String, Value> = LogLevel::ALL
+            .into_iter()
+            .zip(self.levels)
+            .map(|(level, count)| (level.to_string().to_lowercase(), Value::from(count)))
+            .collect();
+        let messages = |messages: &[(String, usize)]| -> Value {
+            messages
+                .iter()
+                .map(|(message, count)| json!({ "message": message, "count": count }))
+                .collect()
+        };
+        json!({
+            "entries": self.entries,
+            "levels": levels,
+            "unleveled": self.unleveled,
+            "first": self.first.map(|time| time.to_rfc3339()),
+            "last": self.last.map(|time| time.to_rfc3339()),
+            "busiest_minute": self.busiest_minute.map(|(minute, count)| {
+                json!({ "minute": minute.to_rfc3339(), "entries": count })
+            }),
+            "top_messages": messages(&self.top_messages),
+            "top_errors": messages(&self.top_errors),
+        })
+    }
+}
+
+/// The message of `entry`: its message field, or its first line without the time and
+/// level it starts with, like `failed to connect` in
+/// `2024-01-01 10:00:00 ERROR failed to connect`.
+fn message(entry: &LogEntry) -> &str {
+    if let Some(message) = entry.message() {
+        return message;
+    }
+    let line = entry.content.lines().next().unwrap_or_default();
+    let line = timestamp::leading(line)
+        .map_or(line, |(_, rest)| rest)
+        .trim_start();
+    // Levels are written in uppercase, like `ERROR`, `[WARN]` or `INFO:`
+    match line.split_once(char::is_whitespace) {
+        Some((word, rest))
+            if !word.chars().any(char::is_lowercase)
+                && LogLevel::parse(word.trim_matches(['[', ']', ':'])).is_some() =>
+        {
+            rest
+        }
+        _ => line,
+    }
+}
+
+/// The `top` messages with the highest counts, most frequent first and then in
+/// alphabetical order.
+fn most_frequent(counts: HashMap<String, usize>, top: usize) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+    counts.truncate(top);
+    counts
+}
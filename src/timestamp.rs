@@ -0,0 +1,266 @@
+//! `timestamp` reads the times written in log entries, either with a format from the
+//! configuration file or by trying the formats logs are commonly written in.
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+};
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// `Timestamp` is a point in time read from a log entry, with the offset it was written in.
+pub type Timestamp = DateTime<FixedOffset>;
+
+/// `OFFSET_FORMATS` are the common formats with a UTC offset, tried after RFC 3339.
+const OFFSET_FORMATS: [&str; 3] = [
+    "%d/%b/%Y:%H:%M:%S %z",
+    "%Y-%m-%d %H:%M:%S%.f %z",
+    "%Y-%m-%dT%H:%M:%S%.f%z",
+];
+
+/// `LOCAL_FORMATS` are the common formats without an offset, read as local time.
+const LOCAL_FORMATS: [&str; 6] = [
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S,%f",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%Y-%m-%d %H:%M",
+    "%b %e %H:%M:%S",
+];
+
+/// `TIME_OF_DAY_FORMATS` are the formats of a time without a date.
+const TIME_OF_DAY_FORMATS: [&str; 2] = ["%H:%M:%S%.f", "%H:%M"];
+
+/// `LEADING_TIME` matches a time at the start of a plain text line, like
+/// `2024-01-01 10:00:00,123`, `[2024-01-01T10:00:00Z]` or `Jan  1 10:00:00`.
+static LEADING_TIME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"^\[?(?P<time>",
+        r"\d{4}[-/]\d{2}[-/]\d{2}[T ]\d{2}:\d{2}(?::\d{2}(?:[.,]\d+)?)?(?:Z|[+-]\d{2}:?\d{2})?",
+        r"|[A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}",
+        r")\]?",
+    ))
+    .expect("leading time pattern is valid")
+});
+
+/// Parse the time `text` of a log entry with the strftime-style `format`, or with the
+/// common formats when there is none. Times without an offset are local times, and
+/// times without a year are in the last year.
+pub fn parse(text: &str, format: Option<&str>) -> Option<Timestamp> {
+    let text = text.trim();
+    if let Some(format) = format {
+        return parse_with(text, format);
+    }
+
+    DateTime::parse_from_rfc3339(text)
+        .ok()
+        .or_else(|| {
+            OFFSET_FORMATS
+                .iter()
+                .find_map(|format| DateTime::parse_from_str(text, format).ok())
+        })
+        .or_else(|| {
+            LOCAL_FORMATS
+                .iter()
+                .find_map(|format| parse_with(text, format))
+        })
+        .or_else(|| parse_epoch(text))
+}
+
+/// Read the time at the start of a plain text `line`, returning it with the rest of
+/// the line after it.
+pub fn leading(line: &str) -> Option<(Timestamp, &str)> {
+    let captures = LEADING_TIME.captures(line)?;
+    let time = parse(&captures["time"], None)?;
+    Some((time, &line[captures[0].len()..]))
+}
+
+/// Parse `text` with the strftime-style `format`.
+fn parse_with(text: &str, format: &str) -> Option<Timestamp> {
+    if let Ok(time) = DateTime::parse_from_str(text, format) {
+        return Some(time);
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(text, format) {
+        return local(time);
+    }
+
+    // Formats like syslog's `%b %e %H:%M:%S` leave out the year
+    let has_year = ["%Y", "%y", "%G", "%g", "%C", "%s", "%D", "%F", "%+", "%c"]
+        .iter()
+        .any(|spec| format.contains(spec));
+    if has_year {
+        return None;
+    }
+    let now = Local::now();
+    let time =
+        NaiveDateTime::parse_from_str(&format!("{} {text}", now.year()), &format!("%Y {format}"))
+            .ok()?;
+    let time = local(time)?;
+    if time > now.fixed_offset() + Duration::days(1) {
+        return time.with_year(now.year() - 1);
+    }
+    Some(time)
+}
+
+/// Parse a Unix time in seconds or milliseconds, like `1700000000` or `1700000000.123`.
+fn parse_epoch(text: &str) -> Option<Timestamp> {
+    let (whole, _) = text.split_once('.').unwrap_or((text, ""));
+    if !(10..=13).contains(&whole.len()) || !whole.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let value: f64 = text.parse().ok()?;
+    let millis = if whole.len() > 10 {
+        value
+    } else {
+        value * 1000.0
+    };
+    DateTime::from_timestamp_millis(millis as i64).map(|time| time.fixed_offset())
+}
+
+/// The local time `time`, or `None` when it doesn't exist, like during a DST change.
+fn local(time: NaiveDateTime) -> Option<Timestamp> {
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|time| time.fixed_offset())
+}
+
+/// Parse a time typed by the user to navigate to. It is a full time in one of the
+/// common formats, a date like `2024-05-01` meaning its midnight, or a time of day like
+/// `14:05` on the day of `reference`.
+pub fn parse_target(text: &str, reference: Option<Timestamp>) -> Option<Timestamp> {
+    if let Ok(date) = NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d") {
+        return local(date.and_time(NaiveTime::MIN));
+    }
+    parse(text, None).or_else(|| {
+        let time = TIME_OF_DAY_FORMATS
+            .iter()
+            .find_map(|format| NaiveTime::parse_from_str(text.trim(), format).ok())?;
+        match reference {
+            Some(reference) => reference
+                .timezone()
+                .from_local_datetime(&reference.date_naive().and_time(time))
+                .earliest(),
+            None => local(Local::now().date_naive().and_time(time)),
+        }
+    })
+}
+
+/// Parse the start of a time range typed on the command line: an age like `30s`,
+/// `15m`, `1h`, `2d` or `1w` before now, or a time `parse_target` understands.
+pub fn parse_since(text: &str) -> Option<Timestamp> {
+    let text = text.trim();
+    let unit = text.chars().last()?;
+    let age: fn(i64) -> Option<Duration> = match unit {
+        's' => Duration::try_seconds,
+        'm' => Duration::try_minutes,
+        'h' => Duration::try_hours,
+        'd' => Duration::try_days,
+        'w' => Duration::try_weeks,
+        // Anything else, like the epoch seconds `1700000000`, is a time
+        _ => return parse_target(text, None),
+    };
+    if let Ok(amount) = text[..text.len() - unit.len_utf8()].parse::<i64>() {
+        return Local::now().fixed_offset().checked_sub_signed(age(amount)?);
+    }
+    parse_target(text, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The UTC time `text` in RFC 3339.
+    fn utc(text: &str) -> Option<Timestamp> {
+        DateTime::parse_from_rfc3339(text).ok()
+    }
+
+    /// The local time `text` in `%Y-%m-%d %H:%M:%S`.
+    fn local_time(text: &str) -> Option<Timestamp> {
+        local(NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").ok()?)
+    }
+
+    #[test]
+    fn parses_common_formats() {
+        let time = utc("2024-05-01T10:00:00Z");
+        assert_eq!(parse("2024-05-01T10:00:00Z", None), time);
+        assert_eq!(parse("2024-05-01T12:00:00+02:00", None), time);
+        assert_eq!(parse("01/May/2024:10:00:00 +0000", None), time);
+        assert_eq!(parse("2024-05-01 10:00:00.000 +0000", None), time);
+        assert_eq!(parse(" 1714557600 ", None), time);
+        assert_eq!(parse("1714557600000", None), time);
+        assert_eq!(
+            parse("2024-05-01 10:00:00", None),
+            local_time("2024-05-01 10:00:00")
+        );
+        assert_eq!(
+            parse("2024/05/01 10:00:00", None),
+            local_time("2024-05-01 10:00:00")
+        );
+        assert!(parse("2024-05-01 10:00:00,250", None).is_some());
+        assert!(parse("Jan  1 10:00:00", None).is_some());
+        assert_eq!(parse("42", None), None);
+        assert_eq!(parse("tomorrow", None), None);
+    }
+
+    #[test]
+    fn parses_configured_formats() {
+        assert_eq!(
+            parse("01.05.2024 10:00:00", Some("%d.%m.%Y %H:%M:%S")),
+            local_time("2024-05-01 10:00:00")
+        );
+        assert_eq!(parse("2024-05-01T10:00:00Z", Some("%d.%m.%Y")), None);
+
+        // Without a year, times are in the last year rather than in the future
+        let time = parse("Jan  1 00:00:00", Some("%b %e %H:%M:%S"));
+        assert!(time.is_some_and(|time| time <= Local::now().fixed_offset()));
+    }
+
+    #[test]
+    fn reads_the_time_a_line_starts_with() {
+        let (time, rest) = leading("2024-05-01 10:00:00,123 ERROR boom").unwrap();
+        assert_eq!(Some(time), parse("2024-05-01 10:00:00,123", None));
+        assert_eq!(rest, " ERROR boom");
+
+        let (time, rest) = leading("[2024-05-01T10:00:00Z] started").unwrap();
+        assert_eq!(Some(time), utc("2024-05-01T10:00:00Z"));
+        assert_eq!(rest, " started");
+
+        assert!(leading("May  1 10:00:00 web1 sshd: hi").is_some());
+        assert!(leading("ERROR 2024-05-01 10:00:00 boom").is_none());
+        assert!(leading("2024-13-45 10:00:00 not a date").is_none());
+    }
+
+    #[test]
+    fn parses_ages_and_times_since() {
+        let age = |text: &str| {
+            let time = parse_since(text)?;
+            Some((Local::now().fixed_offset() - time).num_seconds())
+        };
+        assert!(age("30s").is_some_and(|age| (30..35).contains(&age)));
+        assert!(age("15m").is_some_and(|age| (900..905).contains(&age)));
+        assert!(age("2d").is_some_and(|age| (172_800..172_805).contains(&age)));
+
+        assert_eq!(parse_since("1700000000"), parse("1700000000", None));
+        assert_eq!(
+            parse_since("2024-05-01T10:00:00Z"),
+            utc("2024-05-01T10:00:00Z")
+        );
+        assert_eq!(parse_since("2024-05-01"), local_time("2024-05-01 00:00:00"));
+        assert_eq!(parse_since("12x"), None);
+        assert_eq!(parse_since(""), None);
+    }
+
+    #[test]
+    fn parses_times_of_day_on_the_reference_day() {
+        let reference = utc("2024-05-01T22:00:00Z");
+        assert_eq!(
+            parse_target("14:05", reference),
+            utc("2024-05-01T14:05:00Z")
+        );
+        assert_eq!(
+            parse_target("14:05:30.5", reference),
+            utc("2024-05-01T14:05:30.5Z")
+        );
+        assert_eq!(parse_target("25:00", reference), None);
+    }
+}
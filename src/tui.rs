@@ -1,65 +1,198 @@
 //! TUI module for managing terminal interface with ratatui
 
-use crate::log_entry::LogEntry;
+mod bookmarks;
+mod clipboard;
+mod command;
+mod help;
+mod keymap;
+mod output;
+mod pipe;
+mod state;
+mod table;
+mod theme;
+
+use crate::{
+    ansi,
+    event::{AppEvent, EventLoop},
+    export::ExportFormat,
+    filter::Filter,
+    log_entry::{LogEntry, LogLevel},
+    stats::Stats,
+};
+use bookmarks::{BookmarkPanel, PanelResult};
+pub use clipboard::Clipboard;
+use command::{Command, CommandLine, PromptResult, SetOption};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{
+        DisableMouseCapture, EnableMouseCapture, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
+pub use keymap::KeyMap;
+use keymap::{Action, KeyMatch, KeyPress};
+use output::OutputPanel;
+use pipe::Pipe;
 use ratatui::{
     Frame, Terminal,
     backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    layout::{Constraint, Direction, Layout, Margin, Rect},
+    style::Style,
     text::{Line, Span},
     widgets::{
         Block, Borders, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState,
+        Wrap,
     },
 };
-use std::io::{self, Stdout, stdout};
+use state::{Dedup, StatusMessage, TuiState};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Stdout, stdout},
+    ops::Range,
+    process::ExitStatus,
+    sync::mpsc::Sender,
+    time::{Duration, Instant},
+};
+pub use table::default_columns;
+use table::{ColumnPicker, PickerResult};
+pub use theme::Theme;
 
 /// `CrosstermTerminal` is an alias for the `CrossTerm` backend.
 pub type CrosstermTerminal = Terminal<CrosstermBackend<Stdout>>;
 
+/// `SCROLL_WHEEL_ROWS` is how many rows one notch of the mouse wheel scrolls.
+const SCROLL_WHEEL_ROWS: isize = 3;
+
+/// `DOUBLE_CLICK_INTERVAL` is the longest time between two clicks on the same row
+/// that still counts as a double-click.
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+
+/// `TAB` is what a tab in a log entry is drawn as.
+const TAB: &str = "    ";
+
+/// `NOTE_SYMBOL` starts the row with the note of an entry.
+const NOTE_SYMBOL: &str = "↳";
+
+/// `TOP_MESSAGES` is how many of the most frequent messages the stats panel lists.
+const TOP_MESSAGES: usize = 10;
+
+/// `CHROME_HEIGHT` is the number of rows used by the header, footer and list borders.
+const CHROME_HEIGHT: usize = 6;
+
+/// `TuiRequest` is something the `Tui` needs its owner to do, handed out by `run_loop`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TuiRequest {
+    /// `Reload` reads new entries after the log file changed.
+    Reload,
+    /// `Open` switches to the log file at the path.
+    Open(String),
+    /// `SaveColumns` remembers the columns of the table view for the log file's application.
+    SaveColumns(Vec<String>),
+    /// `SaveBookmarks` remembers the bookmarks and marks of the log file, by the first
+    /// line of their entries.
+    SaveBookmarks(BTreeSet<usize>, BTreeMap<char, usize>),
+    /// `SaveNotes` remembers the notes of the log file, by the first line of their entries.
+    SaveNotes(BTreeMap<usize, String>),
+    /// `Edit` opens the log file in the editor at the line, counting from 0.
+    Edit(usize),
+}
+
+/// `Popups` are the popups drawn over the list when they are open.
+struct Popups<'a> {
+    /// `column_picker` chooses the columns of the table view.
+    column_picker: Option<&'a ColumnPicker>,
+    /// `bookmark_panel` lists the bookmarks and marks.
+    bookmark_panel: Option<&'a BookmarkPanel>,
+    /// `output_panel` shows the output of a piped command.
+    output_panel: Option<&'a mut OutputPanel>,
+}
+
 /// `Tui` manages the terminal user interface using ratatui
 pub struct Tui {
     /// `terminal` is the terminal instance doing all the work.
     terminal: CrosstermTerminal,
-    /// `log_entries` are the log entries that will be displayed to the screen.
-    log_entries: Vec<LogEntry>,
-    /// `scroll_offset` is the amount of offset that the screen has to scroll to show the correct
-    /// log entries.
-    scroll_offset: usize,
-    /// `selected_index` is the current log entry that's highlighted in the UI.
-    selected_index: Option<usize>,
-    /// `auto_scroll` keeps the window at the bottom of the log file when true.
-    auto_scroll: bool, // Track if we should auto-scroll to bottom
+    /// `state` is the view state that gets drawn to the terminal.
+    state: TuiState,
+    /// `dirty` is set whenever the state changed and the screen needs to be redrawn.
+    dirty: bool,
+    /// `list_area` is where the log entries were drawn on the last render, used to map
+    /// mouse events onto rows and the scrollbar.
+    list_area: Rect,
+    /// `last_click` is the time and entry index of the last left click, used to detect
+    /// double-clicks.
+    last_click: Option<(Instant, usize)>,
+    /// `dragging_scrollbar` is true while the left button is held down on the scrollbar.
+    dragging_scrollbar: bool,
+    /// `keymap` binds key sequences to actions.
+    keymap: KeyMap,
+    /// `theme` is the colors everything is drawn with.
+    theme: Theme,
+    /// `clipboard` copies entries and fields out of the `Tui`.
+    clipboard: Clipboard,
+    /// `pending_keys` are the keys typed so far of a multi-key binding like `gg`.
+    pending_keys: Vec<KeyPress>,
+    /// `command_line` is the `:` command input in the footer.
+    command_line: CommandLine,
+    /// `column_picker` is the popup choosing the columns of the table view, when open.
+    column_picker: Option<ColumnPicker>,
+    /// `bookmark_panel` is the popup listing the bookmarks and marks, when open.
+    bookmark_panel: Option<BookmarkPanel>,
+    /// `output_panel` is the popup showing the output of a piped command, when open.
+    output_panel: Option<OutputPanel>,
+    /// `pipe` is the command the entries were piped to while it runs.
+    pipe: Option<Pipe>,
+    /// `events` sends events to the main loop from the threads the `Tui` starts, once
+    /// it runs.
+    events: Option<Sender<AppEvent>>,
+    /// `pending_mark` is the mark action waiting for the key naming the mark.
+    pending_mark: Option<Action>,
+    /// `last_export` is the file the view was last exported to and the format it was
+    /// written in, reused by a bare `:w`.
+    last_export: Option<(String, Option<ExportFormat>)>,
+    /// `requests` are waiting to be handed to the owner of the `Tui`.
+    requests: Vec<TuiRequest>,
 }
 
 impl Tui {
     /// Create a new TUI instance
-    pub fn new() -> io::Result<Self> {
+    pub fn new(keymap: KeyMap, theme: Theme) -> io::Result<Self> {
         let backend = CrosstermBackend::new(stdout());
         let terminal = Terminal::new(backend)?;
+        let viewport_height = (terminal.size()?.height as usize).saturating_sub(CHROME_HEIGHT);
 
         Ok(Self {
             terminal,
-            log_entries: Vec::new(),
-            scroll_offset: 0,
-            selected_index: None,
-            auto_scroll: true,
+            state: TuiState::new(viewport_height),
+            dirty: true,
+            list_area: Rect::default(),
+            last_click: None,
+            dragging_scrollbar: false,
+            keymap,
+            theme,
+            clipboard: Clipboard::default(),
+            pending_keys: Vec::new(),
+            command_line: CommandLine::default(),
+            column_picker: None,
+            bookmark_panel: None,
+            output_panel: None,
+            pipe: None,
+            events: None,
+            pending_mark: None,
+            last_export: None,
+            requests: Vec::new(),
         })
     }
 
     /// Start the TUI by enabling raw mode and entering alternate screen
     pub fn start(&mut self) -> io::Result<()> {
         enable_raw_mode()?;
-        execute!(
-            self.terminal.backend_mut(),
-            EnterAlternateScreen,
-            EnableMouseCapture
-        )?;
+        execute!(self.terminal.backend_mut(), EnterAlternateScreen)?;
+        if self.state.mouse_captured {
+            execute!(self.terminal.backend_mut(), EnableMouseCapture)?;
+        }
         self.terminal.clear()?;
+        self.dirty = true;
         Ok(())
     }
 
@@ -77,10 +210,8 @@ impl Tui {
 
     /// Set all log entries (replaces existing entries)
     pub fn set_log_entries(&mut self, entries: Vec<LogEntry>) {
-        self.log_entries = entries;
-        // Don't auto-scroll on initial load, let user see from the beginning
-        self.auto_scroll = false;
-        self.scroll_offset = 0;
+        self.state.set_log_entries(entries);
+        self.dirty = true;
     }
 
     /// Add new log entries (for when the source only provides new entries)
@@ -89,267 +220,1316 @@ impl Tui {
             return;
         }
 
-        // Check if we're at the bottom AND auto-scroll is enabled
-        let should_auto_scroll = self.auto_scroll && self.is_at_bottom();
+        self.state.append_new_log_entries(new_entries);
+        self.dirty = true;
+    }
 
-        // Add the new entries
-        self.log_entries.extend(new_entries);
+    /// Set the name of the file shown in the header.
+    pub fn set_file_name(&mut self, file_name: String) {
+        self.state.file_name = file_name;
+        self.dirty = true;
+    }
+
+    /// Show the colors of escape sequences in entries, or only their plain text.
+    pub const fn set_ansi(&mut self, ansi: bool) {
+        self.state.ansi = ansi;
+        self.dirty = true;
+    }
 
-        // Only auto-scroll if both conditions are met:
-        // 1. User was already at the bottom
-        // 2. Auto-scroll mode is enabled (not paused)
-        if should_auto_scroll {
-            self.scroll_to_show_latest();
+    /// Set the name of the format of the log file shown in the status line.
+    pub fn set_format_name(&mut self, format_name: &str) {
+        if self.state.format_name != format_name {
+            self.state.format_name = format_name.to_owned();
+            self.dirty = true;
         }
     }
 
-    /// Scroll just enough to show the latest entries (minimal scrolling)
-    fn scroll_to_show_latest(&mut self) {
-        let terminal_height = self.terminal.size().unwrap_or_default().height as usize;
-        let content_height = terminal_height.saturating_sub(4); // Account for borders and title
+    /// Set the lowest level of the entries the error motions jump to.
+    pub const fn set_error_level(&mut self, level: LogLevel) {
+        self.state.error_level = level;
+    }
+
+    /// Set the bookmarks and marks saved for the log file, by the first line of their
+    /// entries.
+    pub fn set_bookmarks(&mut self, bookmarks: BTreeSet<usize>, marks: BTreeMap<char, usize>) {
+        self.state.bookmarks = bookmarks;
+        self.state.marks = marks;
+        self.dirty = true;
+    }
+
+    /// Set the notes saved for the log file, by the first line of their entries.
+    pub fn set_notes(&mut self, notes: BTreeMap<usize, String>) {
+        self.state.notes = notes;
+        self.dirty = true;
+    }
+
+    /// Set how entries and fields are copied to the clipboard.
+    pub fn set_clipboard(&mut self, clipboard: Clipboard) {
+        self.clipboard = clipboard;
+    }
+
+    /// Set or clear the filter deciding which entries are shown.
+    pub fn set_filter(&mut self, filter: Option<Filter>) {
+        self.state.set_filter(filter);
+        self.dirty = true;
+    }
+
+    /// Set the fields shown as columns in the table view.
+    pub fn set_columns(&mut self, columns: Vec<String>) {
+        self.state.set_columns(columns);
+        self.dirty = true;
+    }
 
-        if self.log_entries.len() > content_height {
-            // Calculate the scroll offset to show the last `content_height` entries
-            // This ensures we see a full screen with the newest entries at the bottom
-            let new_scroll_offset = self.log_entries.len().saturating_sub(content_height);
-            self.scroll_offset = new_scroll_offset;
+    /// Show an informational message in the footer.
+    pub fn show_info(&mut self, text: String) {
+        self.state.message = Some(StatusMessage::new(text, false));
+        self.dirty = true;
+    }
+
+    /// Show an error message in the footer.
+    pub fn show_error(&mut self, text: String) {
+        self.state.message = Some(StatusMessage::new(text, true));
+        self.dirty = true;
+    }
+
+    /// Release or re-capture the mouse. While released, the terminal's native
+    /// text selection works.
+    fn toggle_mouse_capture(&mut self) -> io::Result<()> {
+        self.state.mouse_captured = !self.state.mouse_captured;
+        if self.state.mouse_captured {
+            execute!(self.terminal.backend_mut(), EnableMouseCapture)?;
         } else {
-            // If all entries fit on screen, no need to scroll
-            self.scroll_offset = 0;
+            execute!(self.terminal.backend_mut(), DisableMouseCapture)?;
         }
+        Ok(())
     }
 
-    /// Check if the user is currently viewing the bottom of the log
-    fn is_at_bottom(&self) -> bool {
-        if self.log_entries.is_empty() {
-            return true;
+    /// Handle a single event from the `EventLoop` and return whether to continue running.
+    /// File change events are handled by the caller of `run_loop`.
+    pub fn handle_event(&mut self, event: AppEvent) -> io::Result<bool> {
+        match event {
+            AppEvent::Key(key) if key.kind == KeyEventKind::Press => {
+                if self.command_line.is_active() {
+                    return Ok(self.handle_prompt_key(key));
+                }
+                if self.column_picker.is_some() {
+                    self.handle_picker_key(key);
+                    return Ok(true);
+                }
+                if self.bookmark_panel.is_some() {
+                    self.handle_panel_key(key);
+                    return Ok(true);
+                }
+                if let Some(panel) = &mut self.output_panel {
+                    let back = self.keymap.lookup(&[KeyPress::from(key)])
+                        == KeyMatch::Action(Action::Back);
+                    if back || !panel.handle_key(key) {
+                        self.output_panel = None;
+                    }
+                    self.dirty = true;
+                    return Ok(true);
+                }
+                if let Some(action) = self.pending_mark.take() {
+                    self.handle_mark_key(action, key);
+                    return Ok(true);
+                }
+                if let Some(pipe) = &self.pipe
+                    && key.code == KeyCode::Char('c')
+                    && key.modifiers.contains(KeyModifiers::CONTROL)
+                {
+                    match pipe.stop() {
+                        Ok(()) => self.show_info(format!("stopping !{}", pipe.command)),
+                        Err(e) => self.show_error(format!("pipe: {e}")),
+                    }
+                    return Ok(true);
+                }
+                return self.handle_key(key);
+            }
+            AppEvent::Mouse(mouse) => self.handle_mouse(mouse),
+            AppEvent::Resize => self.dirty = true,
+            AppEvent::Tick => {
+                if self
+                    .state
+                    .message
+                    .as_ref()
+                    .is_some_and(StatusMessage::is_expired)
+                {
+                    self.state.message = None;
+                    self.dirty = true;
+                }
+            }
+            AppEvent::FileChanged => self.requests.push(TuiRequest::Reload),
+            AppEvent::Interrupt => return Ok(false),
+            AppEvent::PipeDone(result) => self.show_pipe_output(result),
+            AppEvent::Key(_) => {}
         }
+        Ok(true)
+    }
 
-        let terminal_height = self.terminal.size().unwrap_or_default().height as usize;
-        let content_height = terminal_height.saturating_sub(4); // Account for borders and title
+    /// Handle a key typed into the command line and return whether to continue running.
+    fn handle_prompt_key(&mut self, key: KeyEvent) -> bool {
+        self.dirty = true;
+        match self.command_line.handle_key(key) {
+            PromptResult::Editing | PromptResult::Cancelled => true,
+            PromptResult::Submitted(input) => match Command::parse(&input) {
+                Ok(command) => self.execute(command),
+                Err(e) => {
+                    self.show_error(e);
+                    true
+                }
+            },
+        }
+    }
 
-        if self.log_entries.len() <= content_height {
-            return true; // All entries fit on screen
+    /// Handle a key typed into the column picker. Columns change as they are picked,
+    /// and are saved when the picker is closed.
+    fn handle_picker_key(&mut self, key: KeyEvent) {
+        let Some(picker) = &mut self.column_picker else {
+            return;
+        };
+        self.dirty = true;
+        let result = picker.handle_key(key);
+        let columns = picker.columns();
+        match result {
+            PickerResult::Open => self.state.set_columns(columns),
+            PickerResult::Sort(field) => self.state.cycle_sort(field),
+            PickerResult::Closed => {
+                self.column_picker = None;
+                self.state.set_columns(columns.clone());
+                self.requests.push(TuiRequest::SaveColumns(columns));
+            }
         }
+    }
 
-        let max_scroll = self.log_entries.len().saturating_sub(content_height);
-        self.scroll_offset >= max_scroll
+    /// Handle a key typed into the bookmark panel.
+    fn handle_panel_key(&mut self, key: KeyEvent) {
+        let Some(panel) = &mut self.bookmark_panel else {
+            return;
+        };
+        self.dirty = true;
+        match panel.handle_key(key) {
+            PanelResult::Open => {}
+            PanelResult::Jump(line) => {
+                self.bookmark_panel = None;
+                if let Err(e) = self.state.select_line(line) {
+                    self.show_error(e);
+                }
+            }
+            PanelResult::Delete(line) => {
+                self.state.remove_marks(line);
+                self.save_bookmarks();
+            }
+            PanelResult::Closed => self.bookmark_panel = None,
+        }
     }
 
-    /// Clear all log entries
-    pub fn clear_log_entries(&mut self) {
-        self.log_entries.clear();
-        self.scroll_offset = 0;
-        self.selected_index = None;
-        self.auto_scroll = true; // Re-enable auto-scroll after clearing
+    /// Set or jump to the mark named by the letter typed after the mark action.
+    fn handle_mark_key(&mut self, action: Action, key: KeyEvent) {
+        self.dirty = true;
+        let KeyCode::Char(name) = key.code else {
+            return;
+        };
+        if !name.is_ascii_alphabetic() {
+            self.show_error(format!("'{name}' is not a mark, marks are letters"));
+            return;
+        }
+
+        if action == Action::SetMark {
+            match self.state.set_mark(name) {
+                Ok(()) => {
+                    self.show_info(format!("mark '{name}' set"));
+                    self.save_bookmarks();
+                }
+                Err(e) => self.show_error(e),
+            }
+        } else if let Err(e) = self.state.jump_to_mark(name) {
+            self.show_error(e);
+        }
     }
 
-    /// Scroll to show the latest entries (keeps screen full)
-    pub fn scroll_to_bottom(&mut self) {
-        if !self.log_entries.is_empty() {
-            let terminal_height = self.terminal.size().unwrap_or_default().height as usize;
-            let content_height = terminal_height.saturating_sub(4); // Account for borders and title
+    /// Bookmark the selected entry, or remove its bookmark.
+    fn toggle_bookmark(&mut self) {
+        match self.state.toggle_bookmark() {
+            Ok(bookmarked) => {
+                let text = if bookmarked {
+                    "bookmark added"
+                } else {
+                    "bookmark removed"
+                };
+                self.show_info(text.into());
+                self.save_bookmarks();
+            }
+            Err(e) => self.show_error(e),
+        }
+    }
 
-            if self.log_entries.len() > content_height {
-                // Set scroll offset so the last entry is at the bottom of the visible area
-                self.scroll_offset = self.log_entries.len().saturating_sub(content_height);
-            } else {
-                self.scroll_offset = 0;
-            }
-        }
-    }
-
-    /// Handle keyboard input and return whether to continue running
-    pub fn handle_input(&mut self) -> io::Result<bool> {
-        if event::poll(std::time::Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
-                        KeyCode::Up | KeyCode::Char('k') => {
-                            if self.scroll_offset > 0 {
-                                self.scroll_offset -= 1;
-                                // Disable auto-scroll when user manually scrolls up
-                                self.auto_scroll = false;
-                            }
-                        }
-                        KeyCode::Down | KeyCode::Char('j') => {
-                            let terminal_height = self.terminal.size()?.height as usize;
-                            let content_height = terminal_height.saturating_sub(4);
-                            let max_scroll = self.log_entries.len().saturating_sub(content_height);
-
-                            if self.scroll_offset < max_scroll {
-                                self.scroll_offset += 1;
-                                // Check if we've scrolled back to the bottom
-                                if self.scroll_offset >= max_scroll {
-                                    self.auto_scroll = true;
-                                }
-                            }
-                        }
-                        KeyCode::PageUp => {
-                            let page_size = 10;
-                            self.scroll_offset = self.scroll_offset.saturating_sub(page_size);
-                            self.auto_scroll = false;
-                        }
-                        KeyCode::PageDown => {
-                            let terminal_height = self.terminal.size()?.height as usize;
-                            let content_height = terminal_height.saturating_sub(4);
-                            let max_scroll = self.log_entries.len().saturating_sub(content_height);
-                            let page_size = 10;
-
-                            self.scroll_offset = (self.scroll_offset + page_size).min(max_scroll);
-                            // Check if we've scrolled back to the bottom
-                            if self.scroll_offset >= max_scroll {
-                                self.auto_scroll = true;
-                            }
-                        }
-                        KeyCode::Home => {
-                            self.scroll_offset = 0;
-                            self.auto_scroll = false;
-                        }
-                        KeyCode::End => {
-                            self.scroll_to_bottom();
-                            self.auto_scroll = true;
-                        }
-                        KeyCode::Char('c') => {
-                            self.clear_log_entries();
-                        }
-                        KeyCode::Char('f') => {
-                            // Toggle auto-follow mode
-                            self.auto_scroll = !self.auto_scroll;
-                            if self.auto_scroll {
-                                self.scroll_to_bottom();
-                            }
-                        }
-                        _ => {}
+    /// Ask the owner to save the bookmarks and marks of the log file.
+    fn save_bookmarks(&mut self) {
+        self.requests.push(TuiRequest::SaveBookmarks(
+            self.state.bookmarks.clone(),
+            self.state.marks.clone(),
+        ));
+    }
+
+    /// Collapse repeated entries with `dedup`, or show them all again when `None`.
+    fn set_dedup(&mut self, dedup: Option<Dedup>) {
+        self.state.set_dedup(dedup);
+        let message = match dedup {
+            Some(Dedup::Exact) => "collapsing identical entries",
+            Some(Dedup::Similar) => "collapsing entries differing only in numbers and IDs",
+            None => "showing all entries",
+        };
+        self.show_info(message.into());
+    }
+
+    /// Open the panel summarizing the entries of the visual range, or of the view.
+    fn show_stats(&mut self) {
+        let stats = Stats::collect(self.state.range_entries(), TOP_MESSAGES);
+        let title = match (self.state.visual_rows(), &self.state.filter) {
+            (Some(_), _) => format!("{} (visual range)", self.state.file_name),
+            (None, Some(filter)) => format!("{} ({filter})", self.state.file_name),
+            (None, None) => self.state.file_name.clone(),
+        };
+        self.output_panel = Some(OutputPanel::new("Stats".into(), &stats.report(&title)));
+        self.dirty = true;
+    }
+
+    /// Switch to the table view or back to the list. The table starts out with the
+    /// time, level and message columns when none were chosen.
+    fn show_table(&mut self, table: bool) {
+        if table && self.state.columns.is_empty() {
+            self.state
+                .set_columns(table::default_columns(&self.state.log_entries));
+        }
+        self.state.table = table;
+    }
+
+    /// Open the column picker on the table view.
+    fn open_column_picker(&mut self) {
+        self.show_table(true);
+        self.column_picker = Some(ColumnPicker::new(
+            &self.state.columns,
+            table::known_fields(&self.state.log_entries),
+        ));
+    }
+
+    /// Open the command line with `text` already typed, completing the fields of the
+    /// selected entry.
+    fn open_prompt(&mut self, text: &str) {
+        let fields = self
+            .state
+            .selected_entry()
+            .map(|entry| {
+                entry
+                    .fields
+                    .iter()
+                    .map(|field| field.name.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.command_line.set_fields(fields);
+        self.command_line.open_with(text);
+    }
+
+    /// The `:filter` command the filter action starts with. In a visual range, it
+    /// keeps the current filter and adds the lines of the range.
+    fn filter_prompt(&self) -> String {
+        let Some((first, last)) = self.state.visual_lines() else {
+            return "filter ".to_owned();
+        };
+        let range = Filter::lines(first, last);
+        let filter = match self.state.filter.clone() {
+            Some(filter) => Filter::And(vec![filter, range]),
+            None => range,
+        };
+        format!("filter {filter}")
+    }
+
+    /// Start piping the entries of the visual range, or of the view, to the shell
+    /// command `command`. Its output is shown once it exits, and `Ctrl-C` stops it.
+    fn pipe(&mut self, command: &str) {
+        if let Some(pipe) = &self.pipe {
+            self.show_error(format!(
+                "!{} is still running, Ctrl-C stops it",
+                pipe.command
+            ));
+            return;
+        }
+        let Some(events) = self.events.clone() else {
+            self.show_error("pipe: the TUI isn't running".to_owned());
+            return;
+        };
+        match Pipe::spawn(command, self.state.pipe_input(), events) {
+            Ok(pipe) => {
+                self.show_info(format!("running !{command}, Ctrl-C stops it"));
+                self.pipe = Some(pipe);
+            }
+            Err(e) => self.show_error(format!("pipe: {e}")),
+        }
+    }
+
+    /// Show what the piped command wrote once it exited, in the output panel.
+    fn show_pipe_output(&mut self, result: Result<(String, ExitStatus), String>) {
+        let Some(mut pipe) = self.pipe.take() else {
+            return;
+        };
+        pipe.finish();
+        match result {
+            Ok((output, status)) => {
+                let status = status
+                    .code()
+                    .map_or_else(|| "killed".to_owned(), |code| format!("exit {code}"));
+                let title = format!("!{} ({status})", pipe.command);
+                self.state.visual_anchor = None;
+                self.state.message = None;
+                self.output_panel = Some(OutputPanel::new(title, &output));
+                self.dirty = true;
+            }
+            Err(e) => self.show_error(format!("pipe: {e}")),
+        }
+    }
+
+    /// Copy the field `field` of the selected entry to the clipboard, or the entries of
+    /// the visual range or the selected entry when `None`. Copying ends the visual range.
+    fn yank(&mut self, field: Option<&str>) {
+        let copied = match field {
+            Some(field) => self
+                .state
+                .selected_field(field)
+                .map(|value| (value.to_owned(), field.to_owned())),
+            None => self.state.yank_text().map(|(text, count)| {
+                let what = if count == 1 {
+                    "1 entry".to_owned()
+                } else {
+                    format!("{count} entries")
+                };
+                (text, what)
+            }),
+        };
+        let result = copied.and_then(|(text, what)| {
+            self.clipboard
+                .copy(self.terminal.backend_mut(), &text)
+                .map(|()| what)
+        });
+        match result {
+            Ok(what) => {
+                self.state.visual_anchor = None;
+                self.show_info(format!("copied {what}"));
+            }
+            Err(e) => self.show_error(format!("yank: {e}")),
+        }
+    }
+
+    /// Run a `:` command and return whether to continue running.
+    fn execute(&mut self, command: Command) -> bool {
+        match command {
+            Command::Open(path) => self.requests.push(TuiRequest::Open(path)),
+            Command::Goto(line) => {
+                if let Err(e) = self.state.goto_line(line) {
+                    self.show_error(e);
+                }
+            }
+            Command::Time(time) => {
+                if let Err(e) = self.state.goto_time(&time) {
+                    self.show_error(e);
+                }
+            }
+            Command::Filter(filter) => {
+                let text = filter
+                    .as_ref()
+                    .map_or_else(|| "filter cleared".to_owned(), |f| format!("filter: {f}"));
+                self.state.visual_anchor = None;
+                self.state.set_filter(filter);
+                self.show_info(format!("{text} ({} entries)", self.state.visible.len()));
+            }
+            Command::Columns(None) => self.open_column_picker(),
+            Command::Columns(Some(columns)) => {
+                self.show_table(true);
+                self.state.set_columns(columns.clone());
+                self.requests.push(TuiRequest::SaveColumns(columns));
+            }
+            Command::Sort(sort) => {
+                let text = sort.as_ref().map_or_else(
+                    || "sorted by line".to_owned(),
+                    |(field, descending)| {
+                        let order = if *descending {
+                            "descending"
+                        } else {
+                            "ascending"
+                        };
+                        format!("sorted by {field}, {order}")
+                    },
+                );
+                self.state.set_sort(sort);
+                self.show_info(text);
+            }
+            Command::Set(option) => match option {
+                SetOption::Wrap(wrap) => self.state.wrap = wrap,
+                SetOption::Follow(follow) => self.state.set_follow(follow),
+                SetOption::Number(number) => self.state.line_numbers = number,
+                SetOption::Ansi(ansi) => self.state.ansi = ansi,
+                SetOption::Table(table) => self.show_table(table),
+            },
+            Command::Export(format, path) => {
+                let last = self.last_export.clone();
+                let Some((path, format)) = path.map(|path| (path, format)).or(last) else {
+                    self.show_error("export: no file name".into());
+                    return true;
+                };
+                match self.state.export(&path, format) {
+                    Ok(count) => {
+                        self.state.visual_anchor = None;
+                        self.show_info(format!("wrote {count} entries to {path}"));
+                        self.last_export = Some((path, format));
+                    }
+                    Err(e) => self.show_error(format!("export: {path}: {e}")),
+                }
+            }
+            Command::Note(note) => {
+                let removed = note.is_none();
+                match self.state.set_note(note) {
+                    Ok(line) if removed => {
+                        self.show_info(format!("removed the note on line {line}"))
+                    }
+                    Ok(line) => self.show_info(format!("noted line {line}")),
+                    Err(e) => {
+                        self.show_error(format!("note: {e}"));
+                        return true;
                     }
                 }
+                self.requests
+                    .push(TuiRequest::SaveNotes(self.state.notes.clone()));
+            }
+            Command::Timeline(path) => match self.state.export_timeline(&path) {
+                Ok(count) => self.show_info(format!("wrote {count} notes to {path}")),
+                Err(e) => self.show_error(format!("timeline: {path}: {e}")),
+            },
+            Command::Yank(field) => self.yank(field.as_deref()),
+            Command::Pipe(command) => self.pipe(&command),
+            Command::Stats => self.show_stats(),
+            Command::Dedup(dedup) => self.set_dedup(dedup),
+            Command::Help => self.state.help_scroll = Some(0),
+            Command::Quit => return false,
+        }
+        true
+    }
+
+    /// Handle a key press and return whether to continue running. Keys are collected
+    /// until they complete a binding from the keymap.
+    fn handle_key(&mut self, key: KeyEvent) -> io::Result<bool> {
+        self.pending_keys.push(key.into());
+
+        let action = match self.keymap.lookup(&self.pending_keys) {
+            KeyMatch::Action(action) => action,
+            // Like in vim, the first key of yank copies the visual range right away
+            KeyMatch::Pending
+                if self.state.visual_anchor.is_some()
+                    && self.keymap.starts(&self.pending_keys, Action::Yank) =>
+            {
+                Action::Yank
+            }
+            KeyMatch::Pending => return Ok(true),
+            KeyMatch::None => {
+                // The sequence went nowhere, but the last key may start a new one
+                let retry = self.pending_keys.len() > 1;
+                self.pending_keys.clear();
+                return if retry {
+                    self.handle_key(key)
+                } else {
+                    Ok(true)
+                };
             }
+        };
+
+        self.pending_keys.clear();
+        self.perform(action)
+    }
+
+    /// Perform an action and return whether to continue running
+    fn perform(&mut self, action: Action) -> io::Result<bool> {
+        let half_page = (self.state.viewport_height / 2).max(1).cast_signed();
+
+        if let Some(scroll) = self.state.help_scroll {
+            return Ok(self.perform_in_help(action, scroll));
         }
+
+        match action {
+            Action::Back if self.state.visual_anchor.is_some() => self.state.visual_anchor = None,
+            Action::Back if self.state.show_detail => self.state.show_detail = false,
+            Action::Back | Action::Quit => return Ok(false),
+            Action::ScrollUp => self.state.scroll_by(-1),
+            Action::ScrollDown => self.state.scroll_by(1),
+            Action::PageUp => self.state.page_up(),
+            Action::PageDown => self.state.page_down(),
+            Action::HalfPageUp => self.state.scroll_by(-half_page),
+            Action::HalfPageDown => self.state.scroll_by(half_page),
+            Action::GoToTop => self.state.scroll_to_top(),
+            Action::GoToBottom => self.state.scroll_to_end(),
+            Action::SelectPrevious => self.state.move_selection(-1),
+            Action::SelectNext => self.state.move_selection(1),
+            Action::NextError | Action::PreviousError => {
+                match self.state.jump_to_error(action == Action::NextError) {
+                    Ok(true) => self.show_info("wrapped around".into()),
+                    Ok(false) => {}
+                    Err(e) => self.show_error(e),
+                }
+            }
+            Action::ToggleDetail if self.state.selected_index.is_some() => {
+                // A collapsed run of repeated entries opens before showing details
+                match self.state.expand_selected_run() {
+                    Some(count) => self.show_info(format!("showing {count} repeated entries")),
+                    None => self.state.show_detail = !self.state.show_detail,
+                }
+            }
+            Action::ToggleDetail => return Ok(true),
+            Action::ToggleFold => match self.state.toggle_selected_run() {
+                Some(true) => self.show_info("showing the repeated entries".into()),
+                Some(false) => {}
+                None => {
+                    if let Err(err) = self.state.toggle_fold() {
+                        self.show_error(err);
+                    }
+                }
+            },
+            Action::ToggleAllFolds => self.state.toggle_all_folds(),
+            Action::ToggleTable => self.show_table(!self.state.table),
+            Action::Columns => self.open_column_picker(),
+            Action::ToggleBookmark => self.toggle_bookmark(),
+            Action::Bookmarks => self.bookmark_panel = Some(BookmarkPanel::new(&self.state)),
+            Action::Stats => self.show_stats(),
+            Action::ToggleDedup => {
+                let dedup = self.state.dedup.xor(Some(Dedup::Similar));
+                self.set_dedup(dedup);
+            }
+            Action::SetMark | Action::JumpToMark => self.pending_mark = Some(action),
+            Action::Annotate if self.state.selected_index.is_some() => {
+                let note = self.state.selected_note().unwrap_or_default().to_owned();
+                self.open_prompt(&format!("note {note}"));
+            }
+            Action::Annotate => self.show_error("no entry selected".into()),
+            Action::Yank => self.yank(None),
+            Action::YankField if self.state.selected_index.is_some() => {
+                self.open_prompt("yank ");
+            }
+            Action::YankField => self.show_error("no entry selected".into()),
+            Action::VisualLine => {
+                if let Err(e) = self.state.toggle_visual() {
+                    self.show_error(e);
+                }
+            }
+            Action::Clear => self.state.clear_log_entries(),
+            Action::ToggleFollow => self.state.toggle_follow(),
+            Action::ToggleMouse => self.toggle_mouse_capture()?,
+            Action::Help => self.state.help_scroll = Some(0),
+            Action::Edit => {
+                // Without a selection, the editor opens at the top of the screen
+                let line = self
+                    .state
+                    .selected_entry()
+                    .or_else(|| {
+                        let index = *self.state.visible.get(self.state.scroll_offset)?;
+                        self.state.log_entries.get(index)
+                    })
+                    .map_or(0, |entry| entry.line);
+                self.requests.push(TuiRequest::Edit(line));
+            }
+            Action::CommandLine => self.open_prompt(""),
+            Action::Filter => {
+                let text = self.filter_prompt();
+                self.open_prompt(&text);
+            }
+            Action::Pipe => self.open_prompt("!"),
+        }
+
+        self.dirty = true;
         Ok(true)
     }
 
+    /// Perform an action while the help overlay is open, where movement scrolls the
+    /// overlay. Returns whether to continue running.
+    fn perform_in_help(&mut self, action: Action, scroll: usize) -> bool {
+        let page = self.state.viewport_height.max(1);
+
+        self.state.help_scroll = match action {
+            Action::ScrollDown => Some(scroll + 1),
+            Action::ScrollUp => Some(scroll.saturating_sub(1)),
+            Action::PageDown | Action::HalfPageDown => Some(scroll + page / 2),
+            Action::PageUp | Action::HalfPageUp => Some(scroll.saturating_sub(page / 2)),
+            Action::GoToTop => Some(0),
+            // Clamped to the end of the help text when drawn
+            Action::GoToBottom => Some(usize::MAX),
+            Action::Help | Action::Back => None,
+            Action::Quit => return false,
+            _ => return true,
+        };
+
+        self.dirty = true;
+        true
+    }
+
+    /// Handle a mouse event: the wheel scrolls, a click selects, a double-click opens
+    /// the detail pane and dragging the scrollbar seeks.
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let inner = self.list_area.inner(Margin {
+            vertical: 1,
+            horizontal: 1,
+        });
+        let on_scrollbar = mouse.column + 1 == self.list_area.right()
+            && mouse.row >= inner.top()
+            && mouse.row < inner.bottom()
+            && self.state.max_scroll() > 0;
+
+        if self.handle_overlay_mouse(mouse) {
+            return;
+        }
+        if let Some(scroll) = self.state.help_scroll {
+            match mouse.kind {
+                MouseEventKind::ScrollUp => self.perform_in_help(Action::ScrollUp, scroll),
+                MouseEventKind::ScrollDown => self.perform_in_help(Action::ScrollDown, scroll),
+                _ => true,
+            };
+            return;
+        }
+
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.state.scroll_by(-SCROLL_WHEEL_ROWS),
+            MouseEventKind::ScrollDown => self.state.scroll_by(SCROLL_WHEEL_ROWS),
+            MouseEventKind::Down(MouseButton::Left) if on_scrollbar => {
+                self.dragging_scrollbar = true;
+                self.state
+                    .seek((mouse.row - inner.top()).into(), inner.height.into());
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.dragging_scrollbar => {
+                let row = mouse
+                    .row
+                    .clamp(inner.top(), inner.bottom().saturating_sub(1));
+                self.state
+                    .seek((row - inner.top()).into(), inner.height.into());
+            }
+            MouseEventKind::Up(MouseButton::Left) if self.dragging_scrollbar => {
+                self.dragging_scrollbar = false;
+                return;
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                let position = ratatui::layout::Position::new(mouse.column, mouse.row);
+                let Some(index) = inner
+                    .contains(position)
+                    .then(|| self.state.entry_at_row((mouse.row - inner.top()).into()))
+                    .flatten()
+                else {
+                    return;
+                };
+
+                let now = Instant::now();
+                let double_click = self.last_click.is_some_and(|(at, last)| {
+                    last == index && now.duration_since(at) <= DOUBLE_CLICK_INTERVAL
+                });
+
+                self.state.select(index);
+                if double_click {
+                    self.state.show_detail = true;
+                    self.last_click = None;
+                } else {
+                    self.last_click = Some((now, index));
+                }
+            }
+            _ => return,
+        }
+
+        self.dirty = true;
+    }
+
+    /// Hand the mouse to the popup or prompt taking the keys, if one is open, and
+    /// return whether one was. The wheel moves through popups like the arrow keys,
+    /// and clicks are dropped so that the view behind them stays as it is.
+    fn handle_overlay_mouse(&mut self, mouse: MouseEvent) -> bool {
+        let code = match mouse.kind {
+            MouseEventKind::ScrollUp => Some(KeyCode::Up),
+            MouseEventKind::ScrollDown => Some(KeyCode::Down),
+            _ => None,
+        };
+        if self.command_line.is_active() || self.pending_mark.is_some() {
+            return true;
+        }
+        if self.column_picker.is_some() {
+            if let Some(code) = code {
+                self.handle_picker_key(KeyEvent::from(code));
+            }
+            return true;
+        }
+        if self.bookmark_panel.is_some() {
+            if let Some(code) = code {
+                self.handle_panel_key(KeyEvent::from(code));
+            }
+            return true;
+        }
+        if let Some(panel) = &mut self.output_panel {
+            match code {
+                Some(KeyCode::Up) => panel.scroll_by(-SCROLL_WHEEL_ROWS),
+                Some(_) => panel.scroll_by(SCROLL_WHEEL_ROWS),
+                None => return true,
+            }
+            self.dirty = true;
+            return true;
+        }
+        false
+    }
+
     /// Render the TUI
     pub fn render(&mut self) -> io::Result<()> {
-        let log_entries = &self.log_entries;
-        let scroll_offset = self.scroll_offset;
-        let selected_index = self.selected_index;
-        let auto_scroll = self.auto_scroll;
+        let state = &mut self.state;
+        let keymap = &self.keymap;
+        let theme = &self.theme;
+        let command_line = &self.command_line;
+        let popups = Popups {
+            column_picker: self.column_picker.as_ref(),
+            bookmark_panel: self.bookmark_panel.as_ref(),
+            output_panel: self.output_panel.as_mut(),
+        };
+        let mut list_area = self.list_area;
 
         self.terminal.draw(|frame| {
-            Self::draw_ui_static(
-                frame,
-                log_entries,
-                scroll_offset,
-                selected_index,
-                auto_scroll,
-            );
+            list_area = Self::draw_ui_static(frame, state, keymap, theme, command_line, popups);
         })?;
+
+        self.list_area = list_area;
         Ok(())
     }
 
-    /// Draw the user interface (static version to avoid borrowing issues)
+    /// Draw the user interface (static version to avoid borrowing issues).
+    /// Returns the area the log entries were drawn in.
     fn draw_ui_static(
         frame: &mut Frame,
-        log_entries: &[LogEntry],
-        scroll_offset: usize,
-        selected_index: Option<usize>,
-        auto_scroll: bool,
-    ) {
+        state: &mut TuiState,
+        keymap: &KeyMap,
+        theme: &Theme,
+        command_line: &CommandLine,
+        popups: Popups,
+    ) -> Rect {
         let size = frame.area();
 
         // Create layout
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(3), // Header
+                Constraint::Length(1), // Header
                 Constraint::Min(0),    // Log content
                 Constraint::Length(3), // Footer
             ])
             .split(size);
 
-        // Header
-        let header_text = if auto_scroll {
-            "Log Viewer - Press 'q' to quit, arrow keys to scroll, 'c' to clear, 'f' to toggle follow [FOLLOWING]"
+        // Header with the file name and mode, everything else lives in the help overlay
+        let help_keys = keymap.keys_for(Action::Help);
+        let mut header_text = format!(
+            " {} [{}]",
+            state.file_name,
+            if state.auto_scroll {
+                "FOLLOWING"
+            } else {
+                "PAUSED"
+            }
+        );
+        if let Some(help_key) = help_keys.first() {
+            header_text.push_str(&format!(" - {help_key} for help"));
+        }
+
+        let header = Paragraph::new(header_text).style(theme.header);
+        frame.render_widget(header, chunks[0]);
+
+        // Log entries, with the detail pane below them when open
+        let list_area = if state.show_detail && state.selected_entry().is_some() {
+            let panes = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .split(chunks[1]);
+            Self::draw_detail_static(frame, panes[1], state, keymap, theme);
+            panes[0]
         } else {
-            "Log Viewer - Press 'q' to quit, arrow keys to scroll, 'c' to clear, 'f' to toggle follow [PAUSED]"
+            chunks[1]
         };
 
-        let header = Paragraph::new(header_text)
-            .block(Block::default().borders(Borders::ALL).title("Controls"))
-            .style(Style::default().fg(Color::Cyan));
-        frame.render_widget(header, chunks[0]);
+        // Keep the scroll math in sync with the space the list actually has
+        state.viewport_height = (list_area.height as usize).saturating_sub(2);
+        state.scroll_offset = state.scroll_offset.min(state.max_scroll());
+        Self::draw_log_entries_static(frame, list_area, state, theme);
 
-        // Log entries
-        Self::draw_log_entries_static(frame, chunks[1], log_entries, scroll_offset, selected_index);
-
-        // Footer with status
-        let terminal_height = frame.area().height as usize;
-        let content_height = terminal_height.saturating_sub(4);
-        let status = format!(
-            "Entries: {} | Scroll: {} | Screen: {} | Mode: {} | Use ↑↓/j/k, PgUp/PgDn, Home/End to navigate",
-            log_entries.len(),
-            scroll_offset,
-            content_height,
-            if auto_scroll { "Following" } else { "Paused" }
-        );
-        let footer = Paragraph::new(status)
-            .block(Block::default().borders(Borders::ALL).title("Status"))
-            .style(Style::default().fg(Color::Yellow));
-        frame.render_widget(footer, chunks[2]);
+        Self::draw_footer_static(frame, chunks[2], state, theme, command_line);
+
+        if let Some(picker) = popups.column_picker {
+            let sort = state
+                .sort
+                .as_ref()
+                .map(|(field, descending)| (field.as_str(), *descending));
+            picker.draw(frame, list_area, sort, theme);
+        }
+        if let Some(panel) = popups.bookmark_panel {
+            panel.draw(frame, list_area, theme);
+        }
+        if let Some(panel) = popups.output_panel {
+            panel.draw(frame, list_area, keymap, theme);
+        }
+
+        // Help overlay on top of everything else
+        if let Some(scroll) = state.help_scroll {
+            let max_scroll = help::draw_help(frame, size, keymap, theme, scroll);
+            state.help_scroll = Some(scroll.min(max_scroll));
+        }
+
+        list_area
     }
 
-    /// Draw the log entries list (static version to avoid borrowing issues)
-    fn draw_log_entries_static(
+    /// Draw the footer: the command line while it is open, otherwise the last
+    /// message or the status (static version to avoid borrowing issues)
+    fn draw_footer_static(
         frame: &mut Frame,
         area: Rect,
-        log_entries: &[LogEntry],
-        scroll_offset: usize,
-        selected_index: Option<usize>,
+        state: &TuiState,
+        theme: &Theme,
+        command_line: &CommandLine,
     ) {
-        let terminal_height = area.height as usize;
-        let content_height = terminal_height.saturating_sub(2); // Account for borders
-
-        let visible_entries: Vec<ListItem> = log_entries
-            .iter()
-            .skip(scroll_offset)
-            .take(content_height)
-            .enumerate()
-            .map(|(i, entry)| {
-                let content = entry.content.clone();
-                let style = if Some(i + scroll_offset) == selected_index {
-                    Style::default()
-                        .bg(Color::DarkGray)
-                        .add_modifier(Modifier::BOLD)
-                } else {
-                    Style::default()
-                };
+        if command_line.is_active() {
+            let input = command_line.input();
+            let error = command_line.query_error();
+            // The part of a query from where it went wrong is marked as it is typed
+            let (valid, invalid) =
+                input.split_at(error.as_ref().map_or(input.len(), |e| e.position));
+            let mut spans = vec![
+                Span::raw(format!(":{valid}")),
+                Span::styled(invalid, theme.error),
+            ];
+            if let Some(hint) = command_line.hint() {
+                spans.push(Span::styled(format!("    {hint}"), theme.hint));
+            } else if let Some(error) = error {
+                spans.push(Span::styled(format!("    {}", error.message), theme.error));
+            }
 
-                ListItem::new(Line::from(Span::styled(content, style)))
-            })
-            .collect();
+            let prompt = Paragraph::new(Line::from(spans))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(theme.border)
+                        .title("Command"),
+                )
+                .style(theme.prompt);
+            frame.render_widget(prompt, area);
 
+            let column = area.x + 2 + u16::try_from(command_line.cursor_column()).unwrap_or(0);
+            frame.set_cursor_position((column.min(area.right().saturating_sub(2)), area.y + 1));
+            return;
+        }
+
+        if let Some(message) = &state.message {
+            let style = if message.is_error {
+                theme.error
+            } else {
+                theme.info
+            };
+            let footer = Paragraph::new(message.text.as_str())
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(theme.border)
+                        .title("Status"),
+                )
+                .style(style);
+            frame.render_widget(footer, area);
+            return;
+        }
+
+        let mut status = format!(
+            "Entries: {} | Scroll: {} | Screen: {} | Mode: {} | Mouse: {}",
+            state.log_entries.len(),
+            state.scroll_offset,
+            state.viewport_height,
+            if state.auto_scroll {
+                "Following"
+            } else {
+                "Paused"
+            },
+            if state.mouse_captured { "On" } else { "Off" },
+        );
+        if let Some(rows) = state.visual_rows() {
+            status.push_str(&format!(" | Visual: {} entries", rows.count()));
+        }
+        let (remaining, errors) = state.error_counts();
+        if errors > 0 {
+            status.push_str(&format!(" | Errors: {remaining} of {errors} left"));
+        }
+        if !state.format_name.is_empty() {
+            status.push_str(&format!(" | Format: {}", state.format_name));
+        }
+        if let Some(filter) = &state.filter {
+            status.push_str(&format!(
+                " | Filter: {filter} ({} shown)",
+                state.visible.len()
+            ));
+        }
+        if let Some((field, descending)) = &state.sort {
+            let order = if *descending { "desc" } else { "asc" };
+            status.push_str(&format!(" | Sort: {field} {order}"));
+        }
+        if state.dedup.is_some() {
+            status.push_str(&format!(" | Dedup: {} hidden", state.hidden_duplicates()));
+        }
+
+        let footer = Paragraph::new(status)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.border)
+                    .title("Status"),
+            )
+            .style(theme.status);
+        frame.render_widget(footer, area);
+    }
+
+    /// The scroll offset that keeps the selected entry after it moved (or the last one
+    /// when following) on screen depends on how many rows wrapped and expanded entries
+    /// take up, which is only known while drawing. Scrolls down just enough for it to
+    /// fit.
+    fn fit_rows(state: &mut TuiState, width: usize, theme: &Theme) {
+        let reveal = std::mem::take(&mut state.reveal_selection);
+        let target = if state.auto_scroll {
+            state.visible.len().checked_sub(1)
+        } else {
+            state.selected_index.filter(|_| reveal)
+        };
+        let Some(target) = target.filter(|&target| target >= state.scroll_offset) else {
+            return;
+        };
+
+        let mut rows = 0;
+        let mut first = target + 1;
+        while first > state.scroll_offset {
+            let index = state.visible[first - 1];
+            let entry = &state.log_entries[index];
+            let height = if !state.table && (state.wrap || state.is_expanded(index)) {
+                Self::entry_rows(entry, state, index, false, width, theme).len()
+            } else {
+                1 + usize::from(state.notes.contains_key(&entry.line))
+            };
+            if rows + height > state.viewport_height && first <= target {
+                break;
+            }
+            rows += height;
+            first -= 1;
+        }
+        state.scroll_offset = first;
+    }
+
+    /// The screen rows of `entry`, which is at `index` in the log entries, each with the
+    /// line number to show in front of it. Collapsed multiline entries only show their
+    /// first line, followed by the number of hidden lines.
+    fn entry_rows(
+        entry: &LogEntry,
+        state: &TuiState,
+        index: usize,
+        selected: bool,
+        width: usize,
+        theme: &Theme,
+    ) -> Vec<(Option<usize>, Vec<Span<'static>>)> {
+        let mut lines = Self::split_lines(Self::entry_spans(entry, selected, state.ansi, theme));
+        if lines.len() > 1 && !state.is_expanded(index) {
+            let hidden = lines.len() - 1;
+            lines.truncate(1);
+            let noun = if hidden == 1 { "line" } else { "lines" };
+            lines[0].push(Span::styled(format!(" [+{hidden} {noun}]"), theme.hint));
+        }
+        if let Some(badge) = state.duplicate_badge(index) {
+            lines[0].push(Span::styled(badge, theme.hint));
+        }
+
+        let mut rows = Vec::new();
+        for (i, line) in lines.into_iter().enumerate() {
+            let number = Some(entry.line + i);
+            if state.wrap {
+                let wrapped = Self::wrap_spans(line, width);
+                rows.extend(
+                    wrapped
+                        .into_iter()
+                        .enumerate()
+                        .map(|(part, spans)| ((part == 0).then_some(number).flatten(), spans)),
+                );
+            } else {
+                rows.push((number, line));
+            }
+        }
+        if let Some(note) = state.notes.get(&entry.line) {
+            rows.push((
+                None,
+                vec![Span::styled(format!("{NOTE_SYMBOL} {note}"), theme.note)],
+            ));
+        }
+        rows
+    }
+
+    /// Split `spans` at the newlines between the lines of a multiline entry. Tabs are
+    /// expanded, since the terminal would otherwise draw them over the text.
+    fn split_lines(spans: Vec<Span<'static>>) -> Vec<Vec<Span<'static>>> {
+        let mut lines = vec![Vec::new()];
+        for span in spans {
+            for (i, part) in span.content.split('\n').enumerate() {
+                if i > 0 {
+                    lines.push(Vec::new());
+                }
+                if let Some(line) = lines.last_mut()
+                    && !part.is_empty()
+                {
+                    line.push(Span::styled(part.replace('\t', TAB), span.style));
+                }
+            }
+        }
+        lines
+    }
+
+    /// The styled spans of `entry`. With `ansi`, the colors of escape sequences in the
+    /// entry are drawn on top of the style of its level.
+    fn entry_spans(
+        entry: &LogEntry,
+        selected: bool,
+        ansi: bool,
+        theme: &Theme,
+    ) -> Vec<Span<'static>> {
+        let style = theme.entry(entry.level);
+        let mut spans = match entry.raw.as_deref().filter(|_| ansi) {
+            Some(raw) => ansi::spans(raw, style)
+                .into_iter()
+                .map(|span| {
+                    let style = theme.adapt(span.style);
+                    span.style(style)
+                })
+                .collect(),
+            None if entry.fields.iter().any(|field| field.key_range.is_some()) => {
+                Self::field_spans(entry, style, theme)
+            }
+            None => vec![Span::styled(entry.content.clone(), style)],
+        };
+
+        if selected {
+            for span in &mut spans {
+                span.style = span.style.patch(theme.selected);
+            }
+        }
+        spans
+    }
+
+    /// The spans of an entry with `key=value` fields, with the keys and values of the
+    /// fields highlighted where they are written in the content.
+    fn field_spans(entry: &LogEntry, style: Style, theme: &Theme) -> Vec<Span<'static>> {
+        let mut ranges: Vec<(Range<usize>, Style)> = Vec::new();
+        for field in &entry.fields {
+            if let Some(range) = &field.key_range {
+                ranges.push((range.clone(), style.patch(theme.field_key)));
+            }
+            if let Some(range) = &field.value_range
+                && field.key_range.is_some()
+                && !LogEntry::is_well_known(&field.name)
+            {
+                ranges.push((range.clone(), style.patch(theme.field_value)));
+            }
+        }
+        ranges.sort_by_key(|(range, _)| range.start);
+
+        let content = &entry.content;
+        let mut spans = Vec::new();
+        let mut position = 0;
+        for (range, range_style) in ranges {
+            if range.start < position || range.end > content.len() {
+                continue;
+            }
+            if range.start > position {
+                spans.push(Span::styled(
+                    content[position..range.start].to_owned(),
+                    style,
+                ));
+            }
+            spans.push(Span::styled(content[range.clone()].to_owned(), range_style));
+            position = range.end;
+        }
+        if position < content.len() {
+            spans.push(Span::styled(content[position..].to_owned(), style));
+        }
+        spans
+    }
+
+    /// Split `spans` into rows of at most `width` characters.
+    fn wrap_spans(spans: Vec<Span<'static>>, width: usize) -> Vec<Vec<Span<'static>>> {
+        let mut rows = vec![Vec::new()];
+        let mut row_width = 0;
+        for span in spans {
+            let mut chars = span.content.chars().peekable();
+            while chars.peek().is_some() {
+                if row_width == width {
+                    rows.push(Vec::new());
+                    row_width = 0;
+                }
+                let text: String = chars.by_ref().take(width - row_width).collect();
+                row_width += text.chars().count();
+                if let Some(row) = rows.last_mut() {
+                    row.push(Span::styled(text, span.style));
+                }
+            }
+        }
+        rows
+    }
+
+    /// Draw the log entries list (static version to avoid borrowing issues)
+    fn draw_log_entries_static(frame: &mut Frame, area: Rect, state: &mut TuiState, theme: &Theme) {
+        let content_height = state.viewport_height;
+        let number_width = if state.line_numbers {
+            state
+                .log_entries
+                .last()
+                .map_or(1, |entry| (entry.line + 1).to_string().len())
+                + 1
+        } else {
+            0
+        };
+        // The marks get a column of their own once there are any
+        let marks = !state.bookmarks.is_empty() || !state.marks.is_empty();
+        let gutter_width = number_width + if marks { 2 } else { 0 };
+        // Borders and scrollbar take up three columns
+        let text_width = (area.width as usize)
+            .saturating_sub(3 + gutter_width)
+            .max(1);
+
+        Self::fit_rows(state, text_width, theme);
+
+        // The mark is only shown on the first row of an entry
+        let gutter = |number: Option<usize>, mark: Option<char>| {
+            let mut spans = Vec::new();
+            if marks {
+                let mark = mark.map_or_else(|| "  ".to_owned(), |mark| format!("{mark} "));
+                spans.push(Span::styled(mark, theme.mark));
+            }
+            if state.line_numbers {
+                let number = number
+                    .map(|line| (line + 1).to_string())
+                    .unwrap_or_default();
+                spans.push(Span::styled(
+                    format!("{number:>width$} ", width = number_width - 1),
+                    theme.line_number,
+                ));
+            }
+            spans
+        };
+
+        // The entries of the visual range are highlighted like the selection
+        let visual = state.visual_rows();
+        let is_selected = |row: usize| {
+            Some(row) == state.selected_index
+                || visual.as_ref().is_some_and(|rows| rows.contains(&row))
+        };
+
+        let mut row_map = Vec::with_capacity(content_height);
+        let mut visible_entries: Vec<ListItem> = Vec::new();
+        if state.table {
+            // Every entry is a single row, sized before drawing so the columns line up
+            let rows: Vec<(usize, Vec<String>)> = (state.scroll_offset..state.visible.len())
+                .take(content_height)
+                .map(|row| {
+                    let entry = &state.log_entries[state.visible[row]];
+                    (row, table::cells(entry, &state.columns))
+                })
+                .collect();
+            for (_, cells) in &rows {
+                table::fit_widths(&mut state.column_widths, &state.columns, cells);
+            }
+
+            for (row, cells) in rows {
+                if row_map.len() >= content_height {
+                    break;
+                }
+                let entry = &state.log_entries[state.visible[row]];
+                let mut style = theme.entry(entry.level);
+                if is_selected(row) {
+                    style = style.patch(theme.selected);
+                }
+                let mut spans = gutter(Some(entry.line), state.mark_symbol(entry.line));
+                spans.extend(table::row(&cells, &state.column_widths, style));
+                if let Some(badge) = state.duplicate_badge(state.visible[row]) {
+                    spans.push(Span::styled(badge, theme.hint));
+                }
+                let mut lines = vec![Line::from(spans)];
+                if let Some(note) = state.notes.get(&entry.line) {
+                    let mut spans = gutter(None, None);
+                    spans.push(Span::styled(format!("{NOTE_SYMBOL} {note}"), theme.note));
+                    lines.push(Line::from(spans));
+                }
+                lines.truncate(content_height - row_map.len());
+                row_map.extend(std::iter::repeat_n(row, lines.len()));
+                visible_entries.push(ListItem::new(lines));
+            }
+        } else {
+            for (row, &index) in state.visible.iter().enumerate().skip(state.scroll_offset) {
+                if row_map.len() >= content_height {
+                    break;
+                }
+
+                let entry = &state.log_entries[index];
+                let selected = is_selected(row);
+                let mark = state.mark_symbol(entry.line);
+                let lines: Vec<Line> =
+                    Self::entry_rows(entry, state, index, selected, text_width, theme)
+                        .into_iter()
+                        .take(content_height - row_map.len())
+                        .enumerate()
+                        .map(|(i, (number, chunk))| {
+                            let mut spans = gutter(number, mark.filter(|_| i == 0));
+                            spans.extend(chunk);
+                            Line::from(spans)
+                        })
+                        .collect();
+
+                row_map.extend(std::iter::repeat_n(row, lines.len().max(1)));
+                visible_entries.push(ListItem::new(lines));
+            }
+        }
+        state.row_map = row_map;
+
+        // The table's header takes the place of the title
+        let title = if state.table {
+            let sort = state
+                .sort
+                .as_ref()
+                .map(|(field, descending)| (field.as_str(), *descending));
+            table::header(
+                &state.columns,
+                &state.column_widths,
+                sort,
+                gutter_width,
+                theme,
+            )
+        } else {
+            Line::from("Log Entries")
+        };
         let list = List::new(visible_entries)
-            .block(Block::default().borders(Borders::ALL).title("Log Entries"))
-            .style(Style::default().fg(Color::White));
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.border)
+                    .title(title),
+            )
+            .style(theme.text);
 
         frame.render_widget(list, area);
 
         // Render scrollbar if needed
-        if log_entries.len() > content_height {
+        if state.visible.len() > content_height {
             let mut scrollbar_state = ScrollbarState::default()
-                .content_length(log_entries.len())
+                .content_length(state.max_scroll() + 1)
                 .viewport_content_length(content_height)
-                .position(scroll_offset);
+                .position(state.scroll_offset);
 
             let scrollbar = Scrollbar::default()
                 .orientation(ScrollbarOrientation::VerticalRight)
                 .begin_symbol(Some("↑"))
-                .end_symbol(Some("↓"));
+                .end_symbol(Some("↓"))
+                .style(theme.scrollbar);
 
             frame.render_stateful_widget(
                 scrollbar,
-                area.inner(ratatui::layout::Margin {
+                area.inner(Margin {
                     vertical: 1,
                     horizontal: 0,
                 }),
@@ -358,24 +1538,123 @@ impl Tui {
         }
     }
 
-    /// Run the main TUI loop with optional callback for external events
-    pub fn run_loop<F>(&mut self, mut external_event_handler: F) -> io::Result<()>
+    /// Draw the detail pane for the selected entry (static version to avoid borrowing issues)
+    fn draw_detail_static(
+        frame: &mut Frame,
+        area: Rect,
+        state: &TuiState,
+        keymap: &KeyMap,
+        theme: &Theme,
+    ) {
+        let Some(entry) = state.selected_entry() else {
+            return;
+        };
+
+        let mut lines: Vec<Line> =
+            Self::split_lines(Self::entry_spans(entry, false, state.ansi, theme))
+                .into_iter()
+                .map(Line::from)
+                .collect();
+
+        // Structured entries list their fields below the content, starting with the
+        // time, level and message when they have them
+        if !entry.fields.is_empty() {
+            lines.push(Line::default());
+            let summary: Vec<Span> = [
+                entry
+                    .time()
+                    .map(|time| Span::styled(time.to_owned(), theme.field_key)),
+                entry
+                    .level
+                    .map(|level| Span::styled(level.to_string(), theme.entry(Some(level)))),
+                entry
+                    .message()
+                    .map(|message| Span::styled(message.replace('\n', " "), theme.text)),
+            ]
+            .into_iter()
+            .flatten()
+            .flat_map(|span| [span, Span::raw("  ")])
+            .collect();
+            let has_summary = !summary.is_empty();
+            if has_summary {
+                lines.push(Line::from(summary));
+                lines.push(Line::default());
+            }
+            let width = entry
+                .fields
+                .iter()
+                .map(|field| field.name.chars().count())
+                .max()
+                .unwrap_or(0);
+            for field in entry
+                .fields
+                .iter()
+                .filter(|field| !has_summary || !LogEntry::is_well_known(&field.name))
+            {
+                for (i, value) in field.value.split('\n').enumerate() {
+                    let name = if i == 0 { field.name.as_str() } else { "" };
+                    lines.push(Line::from(vec![
+                        Span::styled(format!("{name:<width$}  "), theme.field_key),
+                        Span::styled(value.replace('\t', TAB), theme.field_value),
+                    ]));
+                }
+            }
+        }
+
+        let detail = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.border)
+                    .title(keymap.closing_title(
+                        &format!("Line {}", entry.line + 1),
+                        &[Action::Back, Action::ToggleDetail],
+                    )),
+            )
+            .style(theme.entry(entry.level));
+        frame.render_widget(detail, area);
+    }
+
+    /// Run the main TUI loop. Every event arrives through `events`; the screen is
+    /// only redrawn when an event changed the state. Requests for the owner of the
+    /// `Tui` are handed to `on_request` after each batch of events, with any number of
+    /// pending file change notifications coalesced into a single `TuiRequest::Reload`.
+    pub fn run_loop<F>(&mut self, events: &EventLoop, mut on_request: F) -> io::Result<()>
     where
-        F: FnMut(&mut Self) -> io::Result<bool>,
+        F: FnMut(&mut Self, TuiRequest) -> io::Result<bool>,
     {
+        self.events = Some(events.sender());
         loop {
-            self.render()?;
+            if self.dirty {
+                self.render()?;
+                self.dirty = false;
+            }
 
-            // Handle TUI input
-            if !self.handle_input()? {
-                break;
+            let first = events.next()?;
+            let mut input = 0;
+            for event in std::iter::once(first).chain(events.drain()) {
+                input += usize::from(event.is_input());
+                if !self.handle_event(event)? {
+                    return Ok(());
+                }
             }
 
-            // Handle external events (like file changes)
-            if !external_event_handler(self)? {
-                break;
+            // Handle external requests (like file changes)
+            let mut requests = std::mem::take(&mut self.requests);
+            requests.dedup();
+            if requests.contains(&TuiRequest::Reload) {
+                // Reading once after everything else keeps the read as late as possible
+                requests.retain(|request| *request != TuiRequest::Reload);
+                requests.push(TuiRequest::Reload);
             }
+            for request in requests {
+                if !on_request(self, request)? {
+                    return Ok(());
+                }
+            }
+            // Input is read again once its requests, like running the editor, are done
+            events.input_handled(input);
         }
-        Ok(())
     }
 }
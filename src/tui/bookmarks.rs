@@ -0,0 +1,179 @@
+//! `bookmarks` holds the panel listing the bookmarked and marked entries.
+
+use super::{
+    TAB,
+    state::{BOOKMARK_SYMBOL, TuiState},
+    theme::Theme,
+};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Flex, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+use std::collections::BTreeSet;
+
+/// `PANEL_HINT` explains the keys of the bookmark panel below its list.
+const PANEL_HINT: &str = "Enter jump  d delete  Esc close";
+
+/// `Bookmark` is a row of the `BookmarkPanel`: an entry with a bookmark or marks.
+#[derive(Debug, Clone)]
+struct Bookmark {
+    /// `line` is the first line of the entry.
+    line: usize,
+    /// `symbols` are the letters of the marks set on the entry, and the bookmark
+    /// symbol when it is bookmarked.
+    symbols: String,
+    /// `preview` is the first line of the entry.
+    preview: String,
+}
+
+/// `PanelResult` is the outcome of a key typed into the `BookmarkPanel`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelResult {
+    /// The panel is still open.
+    Open,
+    /// The entry starting at the line should be selected, closing the panel.
+    Jump(usize),
+    /// The bookmark and marks of the entry starting at the line were deleted.
+    Delete(usize),
+    /// The panel was closed.
+    Closed,
+}
+
+/// `BookmarkPanel` is the popup listing the bookmarked and marked entries in line
+/// order, to jump to one or delete it.
+#[derive(Debug, Clone)]
+pub struct BookmarkPanel {
+    /// `bookmarks` are the listed entries.
+    bookmarks: Vec<Bookmark>,
+    /// `selected` is the index of the highlighted entry.
+    selected: usize,
+}
+
+impl BookmarkPanel {
+    /// Create a `BookmarkPanel` listing the bookmarks and marks of `state`.
+    pub fn new(state: &TuiState) -> Self {
+        let lines: BTreeSet<usize> = state
+            .bookmarks
+            .iter()
+            .chain(state.marks.values())
+            .copied()
+            .collect();
+        let bookmarks = lines
+            .into_iter()
+            .map(|line| {
+                let mut symbols: String = state
+                    .marks
+                    .iter()
+                    .filter(|&(_, &marked)| marked == line)
+                    .map(|(&name, _)| name)
+                    .collect();
+                if state.bookmarks.contains(&line) {
+                    symbols.push(BOOKMARK_SYMBOL);
+                }
+                let preview = state
+                    .entry_at_line(line)
+                    .and_then(|entry| entry.content.lines().next())
+                    .unwrap_or_default()
+                    .replace('\t', TAB);
+                Bookmark {
+                    line,
+                    symbols,
+                    preview,
+                }
+            })
+            .collect();
+        Self {
+            bookmarks,
+            selected: 0,
+        }
+    }
+
+    /// Handle a key typed while the panel is open.
+    pub fn handle_key(&mut self, key: KeyEvent) -> PanelResult {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let last = self.bookmarks.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q' | 'B') => return PanelResult::Closed,
+            KeyCode::Char('g') if control => return PanelResult::Closed,
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1).min(last),
+            KeyCode::Char('n') if control => self.selected = (self.selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char('p') if control => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Enter => {
+                if let Some(bookmark) = self.bookmarks.get(self.selected) {
+                    return PanelResult::Jump(bookmark.line);
+                }
+            }
+            KeyCode::Char('d' | 'x') | KeyCode::Delete if !self.bookmarks.is_empty() => {
+                let bookmark = self.bookmarks.remove(self.selected);
+                self.selected = self.selected.min(self.bookmarks.len().saturating_sub(1));
+                return PanelResult::Delete(bookmark.line);
+            }
+            _ => {}
+        }
+        PanelResult::Open
+    }
+
+    /// Draw the panel centered on top of `area`.
+    pub fn draw(&self, frame: &mut Frame, area: Rect, theme: &Theme) {
+        let number_width = self
+            .bookmarks
+            .last()
+            .map_or(1, |bookmark| (bookmark.line + 1).to_string().len());
+        let mut lines: Vec<Line> = self
+            .bookmarks
+            .iter()
+            .enumerate()
+            .map(|(i, bookmark)| {
+                let style = if i == self.selected {
+                    theme.text.patch(theme.selected)
+                } else {
+                    theme.text
+                };
+                Line::from(vec![
+                    Span::styled(format!(" {:<3}", bookmark.symbols), theme.mark),
+                    Span::styled(
+                        format!("{:>number_width$}  ", bookmark.line + 1),
+                        theme.line_number,
+                    ),
+                    Span::styled(format!("{} ", bookmark.preview), style),
+                ])
+            })
+            .collect();
+        if lines.is_empty() {
+            lines.push(Line::from(Span::styled(" no bookmarks ", theme.hint)));
+        }
+
+        let [popup] = Layout::horizontal([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(area);
+        let height = (lines.len() + 4).min(area.height as usize);
+        let [popup] = Layout::vertical([Constraint::Length(height.try_into().unwrap_or(u16::MAX))])
+            .flex(Flex::Center)
+            .areas(popup);
+
+        // Keep the highlighted entry in view above the hint
+        let viewport = height.saturating_sub(4).max(1);
+        let scroll = self.selected.saturating_sub(viewport - 1);
+        let mut visible: Vec<Line> = lines.into_iter().skip(scroll).take(viewport).collect();
+        visible.push(Line::default());
+        visible.push(Line::from(Span::styled(
+            format!(" {PANEL_HINT}"),
+            theme.hint,
+        )));
+
+        let panel = Paragraph::new(visible)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.border)
+                    .title("Bookmarks"),
+            )
+            .style(theme.text);
+        frame.render_widget(Clear, popup);
+        frame.render_widget(panel, popup);
+    }
+}
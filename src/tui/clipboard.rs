@@ -0,0 +1,92 @@
+//! `clipboard` copies text out of the `Tui` to the system clipboard.
+
+use crate::config::ClipboardConfig;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use std::{
+    env,
+    io::Write,
+    process::{self, Stdio},
+};
+
+/// `Clipboard` copies text with OSC 52 escape sequences, which the terminal puts on
+/// the clipboard of the machine it runs on, and with a local clipboard command.
+#[derive(Debug, Clone)]
+pub struct Clipboard {
+    /// `osc52` sends the text to the terminal in an OSC 52 escape sequence.
+    osc52: bool,
+    /// `command` is the clipboard command and its arguments, if one is configured.
+    command: Option<Vec<String>>,
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self {
+            osc52: true,
+            command: None,
+        }
+    }
+}
+
+impl Clipboard {
+    /// Create the `Clipboard` from the `[clipboard]` config section.
+    pub fn from_config(config: &ClipboardConfig) -> Result<Self, String> {
+        if config.command.as_ref().is_some_and(Vec::is_empty) {
+            return Err("the clipboard command is empty".into());
+        }
+        Ok(Self {
+            osc52: config.osc52,
+            command: config.command.clone(),
+        })
+    }
+
+    /// Copy `text` by writing the OSC 52 escape sequence to `terminal` and running the
+    /// clipboard command.
+    pub fn copy(&self, terminal: &mut impl Write, text: &str) -> Result<(), String> {
+        if !self.osc52 && self.command.is_none() {
+            return Err("OSC 52 is off and no clipboard command is set".into());
+        }
+        if self.osc52 {
+            terminal
+                .write_all(osc52(text).as_bytes())
+                .and_then(|()| terminal.flush())
+                .map_err(|e| e.to_string())?;
+        }
+        if let Some(command) = &self.command {
+            run(command, text).map_err(|e| format!("{}: {e}", command[0]))?;
+        }
+        Ok(())
+    }
+}
+
+/// The escape sequences setting the clipboard to `text`. Inside tmux the sequence is
+/// sent once for tmux itself, which keeps it when `set-clipboard` is on, and once
+/// passed through to the terminal outside, which needs `allow-passthrough`.
+fn osc52(text: &str) -> String {
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    if env::var_os("TMUX").is_none() {
+        return sequence;
+    }
+    let passthrough = format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"));
+    sequence + &passthrough
+}
+
+/// Run the clipboard `command` with `text` on its standard input.
+fn run(command: &[String], text: &str) -> Result<(), String> {
+    let mut child = process::Command::new(&command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+    let status = child.wait().map_err(|e| e.to_string())?;
+    if !status.success() {
+        return Err(format!("exited with {status}"));
+    }
+    Ok(())
+}
@@ -0,0 +1,509 @@
+//! `command` is the ex-style command line at the bottom of the `Tui`, used for
+//! `:` commands.
+
+use super::state::Dedup;
+use crate::{
+    export::ExportFormat,
+    filter::{Filter, QueryError},
+};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{fs, path::Path};
+
+/// `COMMANDS` are the names of all commands, used for completion.
+const COMMANDS: [&str; 18] = [
+    "open", "goto", "time", "filter", "columns", "sort", "note", "timeline", "yank", "pipe",
+    "stats", "dedup", "set", "export", "write", "w", "quit", "help",
+];
+
+/// `DEDUP_MODES` are the arguments `:dedup` understands, used for completion.
+const DEDUP_MODES: [&str; 3] = ["similar", "exact", "off"];
+
+/// `SET_OPTIONS` are the options `:set` understands, used for completion.
+const SET_OPTIONS: [&str; 10] = [
+    "wrap", "nowrap", "follow", "nofollow", "number", "nonumber", "ansi", "noansi", "table",
+    "notable",
+];
+
+/// `PromptResult` is the outcome of a key typed into the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PromptResult {
+    /// The command line is still being edited.
+    Editing,
+    /// The command line was closed without running anything.
+    Cancelled,
+    /// The input was submitted.
+    Submitted(String),
+}
+
+/// `SetOption` is a view option changed with `:set`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOption {
+    /// `Wrap` wraps long entries onto several rows.
+    Wrap(bool),
+    /// `Follow` follows new entries.
+    Follow(bool),
+    /// `Number` shows line numbers.
+    Number(bool),
+    /// `Ansi` shows the colors of escape sequences in entries instead of plain text.
+    Ansi(bool),
+    /// `Table` shows the entries as a table with a column per field.
+    Table(bool),
+}
+
+/// `Command` is a parsed `:` command.
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// `Open` switches to another log file.
+    Open(String),
+    /// `Goto` selects the entry at a 1-based line number.
+    Goto(usize),
+    /// `Time` selects the first entry at or after a time.
+    Time(String),
+    /// `Filter` shows only matching entries, or all entries when `None`.
+    Filter(Option<Filter>),
+    /// `Columns` sets the columns of the table view, or opens the column picker when `None`.
+    Columns(Option<Vec<String>>),
+    /// `Sort` sorts the view by a field, descending when true, or puts it back in line
+    /// order when `None`.
+    Sort(Option<(String, bool)>),
+    /// `Note` sets the note of the selected entry, or removes it when `None`.
+    Note(Option<String>),
+    /// `Timeline` writes the entries with notes to a Markdown file.
+    Timeline(String),
+    /// `Yank` copies a field of the selected entry, or the selected entries when `None`.
+    Yank(Option<String>),
+    /// `Pipe` runs a shell command with the entries of the visual range, or of the view,
+    /// on its standard input.
+    Pipe(String),
+    /// `Stats` opens the panel summarizing the visual range, or the view.
+    Stats,
+    /// `Dedup` collapses repeated entries into one row, or shows them all again when
+    /// `None`.
+    Dedup(Option<Dedup>),
+    /// `Set` changes a view option.
+    Set(SetOption),
+    /// `Export` writes the entries of the visual range, or of the view, to a file, or to
+    /// the last exported file when `None`, in a format or the one of its extension.
+    Export(Option<ExportFormat>, Option<String>),
+    /// `Help` opens the help overlay.
+    Help,
+    /// `Quit` quits logz.
+    Quit,
+}
+
+impl Command {
+    /// Parse the text typed after `:`.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let input = input.trim();
+        // `:!command` is a shorthand for `:pipe command`
+        if let Some(command) = input.strip_prefix('!') {
+            return match command.trim() {
+                "" => Err("!: missing command".into()),
+                command => Ok(Self::Pipe(command.to_owned())),
+            };
+        }
+        let (name, argument) = input
+            .split_once(char::is_whitespace)
+            .map_or((input, ""), |(name, argument)| (name, argument.trim()));
+
+        // `:1234` is a shorthand for `:goto 1234`
+        if let Ok(line) = name.parse::<usize>() {
+            return Ok(Self::Goto(line));
+        }
+
+        let require = |what: &str| -> Result<String, String> {
+            if argument.is_empty() {
+                Err(format!("{name}: missing {what}"))
+            } else {
+                Ok(argument.to_owned())
+            }
+        };
+
+        match name {
+            "open" | "o" | "e" | "edit" => Ok(Self::Open(require("file path")?)),
+            "goto" | "g" => require("line number")?
+                .parse()
+                .map(Self::Goto)
+                .map_err(|_| format!("goto: '{argument}' is not a line number")),
+            "time" | "t" => Ok(Self::Time(require("time")?)),
+            "filter" | "f" if argument.is_empty() => Ok(Self::Filter(None)),
+            "filter" | "f" => Filter::parse(argument)
+                .map(|filter| Self::Filter(Some(filter)))
+                .map_err(|e| format!("filter: {e}")),
+            "columns" | "cols" if argument.is_empty() => Ok(Self::Columns(None)),
+            "columns" | "cols" => Ok(Self::Columns(Some(
+                argument
+                    .split([',', ' '])
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_owned)
+                    .collect(),
+            ))),
+            "sort" => Self::parse_sort(argument),
+            "note" | "annotate" => Ok(Self::Note(
+                Some(argument.to_owned()).filter(|a| !a.is_empty()),
+            )),
+            "timeline" => Ok(Self::Timeline(require("file path")?)),
+            "yank" | "y" => Ok(Self::Yank(
+                Some(argument.to_owned()).filter(|a| !a.is_empty()),
+            )),
+            "pipe" => Ok(Self::Pipe(require("command")?)),
+            "stats" => Ok(Self::Stats),
+            "dedup" => match argument {
+                "" | "similar" => Ok(Self::Dedup(Some(Dedup::Similar))),
+                "exact" => Ok(Self::Dedup(Some(Dedup::Exact))),
+                "off" => Ok(Self::Dedup(None)),
+                _ => Err(format!(
+                    "dedup: expected similar, exact or off, not '{argument}'"
+                )),
+            },
+            "set" => Self::parse_set(&require("option")?),
+            "export" | "write" | "w" => Ok(Self::parse_export(argument)),
+            "help" | "h" => Ok(Self::Help),
+            "quit" | "q" => Ok(Self::Quit),
+            "" => Err("no command".into()),
+            _ => Err(format!("unknown command '{name}'")),
+        }
+    }
+
+    /// Parse the argument of `:export`: a path, optionally after a format name.
+    fn parse_export(argument: &str) -> Self {
+        if let Some((name, path)) = argument.split_once(' ')
+            && let Some(format) = ExportFormat::parse(name)
+        {
+            return Self::Export(Some(format), Some(path.trim_start().to_owned()));
+        }
+        Self::Export(None, Some(argument.to_owned()).filter(|a| !a.is_empty()))
+    }
+
+    /// Parse the argument of `:sort`: a field name optionally followed by `asc` or
+    /// `desc`, or nothing to stop sorting.
+    fn parse_sort(argument: &str) -> Result<Self, String> {
+        let mut words = argument.split_whitespace();
+        let Some(field) = words.next() else {
+            return Ok(Self::Sort(None));
+        };
+        let descending = match words.next() {
+            None | Some("asc") => false,
+            Some("desc") => true,
+            Some(other) => return Err(format!("sort: expected asc or desc, not '{other}'")),
+        };
+        Ok(Self::Sort(Some((field.to_owned(), descending))))
+    }
+
+    /// Parse the option of `:set`.
+    fn parse_set(option: &str) -> Result<Self, String> {
+        let (name, enabled) = option
+            .strip_prefix("no")
+            .map_or((option, true), |name| (name, false));
+        let option = match name {
+            "wrap" => SetOption::Wrap(enabled),
+            "follow" => SetOption::Follow(enabled),
+            "number" | "nu" => SetOption::Number(enabled),
+            "ansi" => SetOption::Ansi(enabled),
+            "table" => SetOption::Table(enabled),
+            _ => return Err(format!("set: unknown option '{option}'")),
+        };
+        Ok(Self::Set(option))
+    }
+}
+
+/// `CommandLine` is the editable input line with its history.
+#[derive(Debug, Default)]
+pub struct CommandLine {
+    /// `active` is set while the command line is open.
+    active: bool,
+    /// `input` is the text typed so far.
+    input: String,
+    /// `cursor` is the byte position of the cursor in `input`.
+    cursor: usize,
+    /// `history` are previously submitted commands, oldest first.
+    history: Vec<String>,
+    /// `history_index` is the history entry being shown while browsing with up/down.
+    history_index: Option<usize>,
+    /// `draft` is the input that was typed before browsing the history.
+    draft: String,
+    /// `hint` lists the completion candidates after an ambiguous tab completion.
+    hint: Option<String>,
+    /// `fields` are the field names of the selected entry, completed after `:yank`.
+    fields: Vec<String>,
+}
+
+impl CommandLine {
+    /// Open the command line for a new command.
+    pub fn open(&mut self) {
+        self.active = true;
+        self.input.clear();
+        self.cursor = 0;
+        self.history_index = None;
+        self.hint = None;
+    }
+
+    /// Open the command line for a new command, with `text` already typed.
+    pub fn open_with(&mut self, text: &str) {
+        self.open();
+        self.input.push_str(text);
+        self.cursor = self.input.len();
+    }
+
+    /// Set the field names of the selected entry, completed after `:yank`.
+    pub fn set_fields(&mut self, fields: Vec<String>) {
+        self.fields = fields;
+    }
+
+    /// `is_active` checks if the command line is open.
+    pub const fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// `input` is the text typed so far.
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// `cursor_column` is the column of the cursor within the input.
+    pub fn cursor_column(&self) -> usize {
+        self.input[..self.cursor].chars().count()
+    }
+
+    /// `hint` lists the completion candidates after an ambiguous tab completion.
+    pub fn hint(&self) -> Option<&str> {
+        self.hint.as_deref()
+    }
+
+    /// `query_error` is what is wrong with the query of the `:filter` being typed, with
+    /// its position in the input, so that it shows before the command is submitted.
+    pub fn query_error(&self) -> Option<QueryError> {
+        if !self.active {
+            return None;
+        }
+        let (name, argument) = self.input.trim_start().split_once(char::is_whitespace)?;
+        if !matches!(name, "filter" | "f") || argument.trim().is_empty() {
+            return None;
+        }
+        let mut error = Filter::parse(argument).err()?;
+        error.position += self.input.len() - argument.len();
+        error.column = self.input[..error.position].chars().count() + 1;
+        Some(error)
+    }
+
+    /// Handle a key typed while the command line is open.
+    pub fn handle_key(&mut self, key: KeyEvent) -> PromptResult {
+        if !self.active {
+            return PromptResult::Cancelled;
+        }
+
+        if key.code != KeyCode::Tab {
+            self.hint = None;
+        }
+
+        match key.code {
+            KeyCode::Esc => return self.close(),
+            KeyCode::Char('c' | 'g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return self.close();
+            }
+            KeyCode::Backspace if self.input.is_empty() => return self.close(),
+            KeyCode::Enter => {
+                let input = std::mem::take(&mut self.input);
+                self.active = false;
+                if !input.trim().is_empty() {
+                    self.history.retain(|previous| *previous != input);
+                    self.history.push(input.clone());
+                }
+                return PromptResult::Submitted(input);
+            }
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.input.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let start = self.input[..self.cursor]
+                    .trim_end()
+                    .rfind(char::is_whitespace)
+                    .map_or(0, |i| i + 1);
+                self.input.drain(start..self.cursor);
+                self.cursor = start;
+            }
+            KeyCode::Char(c) => {
+                self.input.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+            }
+            KeyCode::Backspace => {
+                if let Some(c) = self.input[..self.cursor].chars().next_back() {
+                    self.cursor -= c.len_utf8();
+                    self.input.remove(self.cursor);
+                }
+            }
+            KeyCode::Delete if self.cursor < self.input.len() => {
+                self.input.remove(self.cursor);
+            }
+            KeyCode::Left => {
+                if let Some(c) = self.input[..self.cursor].chars().next_back() {
+                    self.cursor -= c.len_utf8();
+                }
+            }
+            KeyCode::Right => {
+                if let Some(c) = self.input[self.cursor..].chars().next() {
+                    self.cursor += c.len_utf8();
+                }
+            }
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.input.len(),
+            KeyCode::Up => self.browse_history(true),
+            KeyCode::Down => self.browse_history(false),
+            KeyCode::Tab => self.complete(),
+            _ => {}
+        }
+
+        PromptResult::Editing
+    }
+
+    /// Close the command line without submitting.
+    fn close(&mut self) -> PromptResult {
+        self.active = false;
+        self.input.clear();
+        self.cursor = 0;
+        PromptResult::Cancelled
+    }
+
+    /// Step through the history, `older` going back in time.
+    fn browse_history(&mut self, older: bool) {
+        let len = self.history.len();
+        let index = match (self.history_index, older) {
+            (None, true) if len > 0 => {
+                self.draft = self.input.clone();
+                Some(len - 1)
+            }
+            (None, _) => return,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) if index + 1 < len => Some(index + 1),
+            (Some(_), false) => None,
+        };
+
+        self.history_index = index;
+        self.input = match index {
+            Some(index) => self.history[index].clone(),
+            None => std::mem::take(&mut self.draft),
+        };
+        self.cursor = self.input.len();
+    }
+
+    /// Complete the command name, `:set` option, field or file path before the cursor.
+    fn complete(&mut self) {
+        let before = &self.input[..self.cursor];
+        let (start, candidates) = match before.split_once(char::is_whitespace) {
+            None => (
+                0,
+                COMMANDS
+                    .iter()
+                    .filter(|name| name.starts_with(before))
+                    .map(|&name| name.to_owned())
+                    .collect(),
+            ),
+            Some((name, argument)) => {
+                let mut start = before.len() - argument.trim_start().len();
+                let mut argument = &before[start..];
+                let mut after_format = false;
+                // `:export csv <path>` completes the path after the format
+                if matches!(name, "export" | "write" | "w")
+                    && let Some((format, path)) = argument.split_once(' ')
+                    && ExportFormat::parse(format).is_some()
+                {
+                    start = before.len() - path.trim_start().len();
+                    argument = &before[start..];
+                    after_format = true;
+                }
+                let candidates = match name {
+                    "set" => SET_OPTIONS
+                        .iter()
+                        .filter(|option| option.starts_with(argument))
+                        .map(|&option| option.to_owned())
+                        .collect(),
+                    "dedup" => DEDUP_MODES
+                        .iter()
+                        .filter(|mode| mode.starts_with(argument))
+                        .map(|&mode| mode.to_owned())
+                        .collect(),
+                    "export" | "write" | "w" if after_format => complete_path(argument),
+                    "export" | "write" | "w" => ExportFormat::NAMES
+                        .iter()
+                        .filter(|format| !argument.is_empty() && format.starts_with(argument))
+                        .map(|&format| format.to_owned())
+                        .chain(complete_path(argument))
+                        .collect(),
+                    "open" | "o" | "e" | "edit" | "timeline" => complete_path(argument),
+                    "yank" | "y" => self
+                        .fields
+                        .iter()
+                        .filter(|field| field.starts_with(argument))
+                        .cloned()
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                (start, candidates)
+            }
+        };
+
+        let Some(common) = common_prefix(&candidates) else {
+            return;
+        };
+
+        let mut replacement = common;
+        if candidates.len() == 1 && !replacement.ends_with('/') {
+            replacement.push(' ');
+        }
+        if replacement.len() > self.cursor - start {
+            self.input.replace_range(start..self.cursor, &replacement);
+            self.cursor = start + replacement.len();
+        }
+        if candidates.len() > 1 {
+            self.hint = Some(candidates.join("  "));
+        }
+    }
+}
+
+/// List the files and directories starting with `partial`. Directories end in `/`.
+fn complete_path(partial: &str) -> Vec<String> {
+    let (dir_part, file_prefix) = partial
+        .rfind('/')
+        .map_or(("", partial), |i| (&partial[..=i], &partial[i + 1..]));
+    let dir = if dir_part.is_empty() {
+        ".".to_owned()
+    } else {
+        shellexpand::tilde(dir_part).into_owned()
+    };
+
+    let Ok(read_dir) = fs::read_dir(Path::new(&dir)) else {
+        return Vec::new();
+    };
+
+    let mut candidates: Vec<String> = read_dir
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let hidden = name.starts_with('.') && !file_prefix.starts_with('.');
+            if hidden || !name.starts_with(file_prefix) {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{dir_part}{name}{slash}"))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+/// The longest prefix shared by all candidates, or `None` when there are none.
+fn common_prefix(candidates: &[String]) -> Option<String> {
+    let first = candidates.first()?;
+    let mut len = first.len();
+    for candidate in &candidates[1..] {
+        len = first
+            .char_indices()
+            .zip(candidate.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8())
+            .min(len);
+    }
+    Some(first[..len].to_owned())
+}
@@ -0,0 +1,101 @@
+//! `help` draws the help overlay listing every keybinding from the keymap.
+
+use super::{
+    keymap::{Action, KeyMap},
+    theme::Theme,
+};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Flex, Layout, Margin, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState},
+};
+
+/// `KEYS_WIDTH` is the width of the column holding the keys of an action.
+const KEYS_WIDTH: usize = 22;
+
+/// Build the lines of the help overlay, grouped by category in the order
+/// the categories first appear in `Action::ALL`.
+pub fn help_lines(keymap: &KeyMap, theme: &Theme) -> Vec<Line<'static>> {
+    let mut categories: Vec<&str> = Vec::new();
+    for action in Action::ALL {
+        if !categories.contains(&action.category()) {
+            categories.push(action.category());
+        }
+    }
+
+    let mut lines = Vec::new();
+    for category in categories {
+        if !lines.is_empty() {
+            lines.push(Line::default());
+        }
+        lines.push(Line::from(Span::styled(category, theme.help_heading)));
+
+        for action in Action::ALL.into_iter().filter(|a| a.category() == category) {
+            let keys = keymap.keys_for(action);
+            let keys = if keys.is_empty() {
+                "(unbound)".to_owned()
+            } else {
+                keys.join(" ")
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {keys:<KEYS_WIDTH$}"), theme.help_key),
+                Span::raw(action.description()),
+            ]));
+        }
+    }
+    lines
+}
+
+/// Draw the help overlay centered on top of `area`, scrolled down by `scroll` lines.
+/// Returns the largest useful scroll offset so the caller can clamp it.
+pub fn draw_help(
+    frame: &mut Frame,
+    area: Rect,
+    keymap: &KeyMap,
+    theme: &Theme,
+    scroll: usize,
+) -> usize {
+    let lines = help_lines(keymap, theme);
+    let width = lines.iter().map(Line::width).max().unwrap_or_default() + 4;
+
+    let [popup] = Layout::horizontal([Constraint::Length(width.try_into().unwrap_or(u16::MAX))])
+        .flex(Flex::Center)
+        .areas(area);
+    let [popup] = Layout::vertical([Constraint::Percentage(80)])
+        .flex(Flex::Center)
+        .areas(popup);
+
+    let viewport = popup.height.saturating_sub(2) as usize;
+    let max_scroll = lines.len().saturating_sub(viewport);
+    let scroll = scroll.min(max_scroll);
+
+    let help = Paragraph::new(lines)
+        .scroll((scroll.try_into().unwrap_or(u16::MAX), 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(theme.border)
+                .title(keymap.closing_title("Help", &[Action::Back, Action::Help])),
+        )
+        .style(theme.text);
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(help, popup);
+
+    if max_scroll > 0 {
+        let mut scrollbar_state = ScrollbarState::new(max_scroll + 1).position(scroll);
+        frame.render_stateful_widget(
+            Scrollbar::default()
+                .orientation(ScrollbarOrientation::VerticalRight)
+                .style(theme.scrollbar),
+            popup.inner(Margin {
+                vertical: 1,
+                horizontal: 0,
+            }),
+            &mut scrollbar_state,
+        );
+    }
+
+    max_scroll
+}
@@ -0,0 +1,613 @@
+//! `keymap` binds key sequences to named `Tui` actions.
+
+use crate::config::KeysConfig;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{collections::BTreeMap, fmt};
+
+/// `Action` is everything the user can trigger with a key in the `Tui`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    /// Scroll down one entry.
+    ScrollDown,
+    /// Scroll up one entry.
+    ScrollUp,
+    /// Scroll down one page.
+    PageDown,
+    /// Scroll up one page.
+    PageUp,
+    /// Scroll down half a screen.
+    HalfPageDown,
+    /// Scroll up half a screen.
+    HalfPageUp,
+    /// Scroll to the first entry.
+    GoToTop,
+    /// Scroll to the last entry and follow the log.
+    GoToBottom,
+    /// Move the selection down one entry.
+    SelectNext,
+    /// Move the selection up one entry.
+    SelectPrevious,
+    /// Select the next entry at the error level or above.
+    NextError,
+    /// Select the previous entry at the error level or above.
+    PreviousError,
+    /// Open or close the detail pane for the selected entry.
+    ToggleDetail,
+    /// Expand or collapse the lines of the selected multiline entry.
+    ToggleFold,
+    /// Expand or collapse every multiline entry.
+    ToggleAllFolds,
+    /// Switch between the list and the table view.
+    ToggleTable,
+    /// Open the picker choosing the columns of the table view.
+    Columns,
+    /// Bookmark the selected entry, or remove its bookmark.
+    ToggleBookmark,
+    /// Open the panel listing the bookmarks and marks.
+    Bookmarks,
+    /// Set the mark named by the next key on the selected entry.
+    SetMark,
+    /// Select the entry of the mark named by the next key.
+    JumpToMark,
+    /// Open the command line to write the note of the selected entry.
+    Annotate,
+    /// Copy the selected entry, or the entries of the visual range.
+    Yank,
+    /// Open the command line to copy a field of the selected entry.
+    YankField,
+    /// Start a visual range at the selected entry, or end it.
+    VisualLine,
+    /// Open the command line to pipe the visual range, or the view, to a shell command.
+    Pipe,
+    /// Toggle following new entries.
+    ToggleFollow,
+    /// Release or re-capture the mouse.
+    ToggleMouse,
+    /// Clear all entries from the view.
+    Clear,
+    /// Open the panel summarizing the visual range, or the view.
+    Stats,
+    /// Collapse repeated entries into one row, or show them all again.
+    ToggleDedup,
+    /// Open the command line with a `:filter` query.
+    Filter,
+    /// Open the log file in the editor at the selected entry.
+    Edit,
+    /// Open the `:` command line.
+    CommandLine,
+    /// Open or close the help overlay.
+    Help,
+    /// Close whatever is open, or quit when nothing is.
+    Back,
+    /// Quit logz.
+    Quit,
+}
+
+impl Action {
+    /// `ALL` lists every action.
+    pub const ALL: [Self; 37] = [
+        Self::ScrollDown,
+        Self::ScrollUp,
+        Self::PageDown,
+        Self::PageUp,
+        Self::HalfPageDown,
+        Self::HalfPageUp,
+        Self::GoToTop,
+        Self::GoToBottom,
+        Self::SelectNext,
+        Self::SelectPrevious,
+        Self::NextError,
+        Self::PreviousError,
+        Self::ToggleDetail,
+        Self::ToggleFold,
+        Self::ToggleAllFolds,
+        Self::ToggleTable,
+        Self::Columns,
+        Self::ToggleBookmark,
+        Self::Bookmarks,
+        Self::SetMark,
+        Self::JumpToMark,
+        Self::Annotate,
+        Self::Yank,
+        Self::YankField,
+        Self::VisualLine,
+        Self::Pipe,
+        Self::ToggleFollow,
+        Self::ToggleMouse,
+        Self::Clear,
+        Self::Stats,
+        Self::ToggleDedup,
+        Self::Filter,
+        Self::Edit,
+        Self::CommandLine,
+        Self::Help,
+        Self::Back,
+        Self::Quit,
+    ];
+
+    /// `name` is the name used for the action in the `[keys]` config section.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::ScrollDown => "scroll_down",
+            Self::ScrollUp => "scroll_up",
+            Self::PageDown => "page_down",
+            Self::PageUp => "page_up",
+            Self::HalfPageDown => "half_page_down",
+            Self::HalfPageUp => "half_page_up",
+            Self::GoToTop => "go_to_top",
+            Self::GoToBottom => "go_to_bottom",
+            Self::SelectNext => "select_next",
+            Self::SelectPrevious => "select_previous",
+            Self::NextError => "next_error",
+            Self::PreviousError => "previous_error",
+            Self::ToggleDetail => "toggle_detail",
+            Self::ToggleFold => "toggle_fold",
+            Self::ToggleAllFolds => "toggle_all_folds",
+            Self::ToggleTable => "toggle_table",
+            Self::Columns => "columns",
+            Self::ToggleBookmark => "toggle_bookmark",
+            Self::Bookmarks => "bookmarks",
+            Self::SetMark => "set_mark",
+            Self::JumpToMark => "jump_to_mark",
+            Self::Annotate => "annotate",
+            Self::Yank => "yank",
+            Self::YankField => "yank_field",
+            Self::VisualLine => "visual_line",
+            Self::Pipe => "pipe",
+            Self::ToggleFollow => "toggle_follow",
+            Self::ToggleMouse => "toggle_mouse",
+            Self::Clear => "clear",
+            Self::Stats => "stats",
+            Self::ToggleDedup => "toggle_dedup",
+            Self::Filter => "filter",
+            Self::Edit => "edit",
+            Self::CommandLine => "command_line",
+            Self::Help => "help",
+            Self::Back => "back",
+            Self::Quit => "quit",
+        }
+    }
+
+    /// `category` is the heading the action is listed under in the help overlay.
+    pub const fn category(self) -> &'static str {
+        match self {
+            Self::ScrollDown
+            | Self::ScrollUp
+            | Self::PageDown
+            | Self::PageUp
+            | Self::HalfPageDown
+            | Self::HalfPageUp
+            | Self::GoToTop
+            | Self::GoToBottom
+            | Self::SelectNext
+            | Self::SelectPrevious
+            | Self::NextError
+            | Self::PreviousError => "Navigation",
+            Self::ToggleBookmark
+            | Self::Bookmarks
+            | Self::SetMark
+            | Self::JumpToMark
+            | Self::Annotate => "Marks",
+            Self::Yank | Self::YankField | Self::VisualLine | Self::Pipe => "Selection",
+            Self::ToggleDetail
+            | Self::ToggleFold
+            | Self::ToggleAllFolds
+            | Self::ToggleTable
+            | Self::Columns
+            | Self::ToggleFollow
+            | Self::ToggleMouse
+            | Self::Clear
+            | Self::Stats
+            | Self::ToggleDedup => "View",
+            Self::Filter => "Filter",
+            Self::Edit | Self::CommandLine | Self::Help | Self::Back | Self::Quit => "General",
+        }
+    }
+
+    /// `description` explains the action in the help overlay.
+    pub const fn description(self) -> &'static str {
+        match self {
+            Self::ScrollDown => "Scroll down one entry",
+            Self::ScrollUp => "Scroll up one entry",
+            Self::PageDown => "Scroll down one page",
+            Self::PageUp => "Scroll up one page",
+            Self::HalfPageDown => "Scroll down half a screen",
+            Self::HalfPageUp => "Scroll up half a screen",
+            Self::GoToTop => "Go to the first entry",
+            Self::GoToBottom => "Go to the last entry and follow",
+            Self::SelectNext => "Select the next entry",
+            Self::SelectPrevious => "Select the previous entry",
+            Self::NextError => "Go to the next problem entry",
+            Self::PreviousError => "Go to the previous problem entry",
+            Self::ToggleDetail => "Show details of the selected entry",
+            Self::ToggleFold => "Expand or collapse a multiline entry",
+            Self::ToggleAllFolds => "Expand or collapse all multiline entries",
+            Self::ToggleTable => "Switch between the list and the table of fields",
+            Self::Columns => "Choose, order and sort the columns of the table",
+            Self::ToggleBookmark => "Bookmark the selected entry, or remove its bookmark",
+            Self::Bookmarks => "List the bookmarks and marks",
+            Self::SetMark => "Set a mark, like ma for mark a",
+            Self::JumpToMark => "Jump to a mark, like 'a for mark a",
+            Self::Annotate => "Write a note on the selected entry (:note, :timeline)",
+            Self::Yank => "Copy the selected entry, or the visual range, to the clipboard",
+            Self::YankField => "Copy a field of the selected entry (:yank)",
+            Self::VisualLine => "Select a range of entries to copy, write (:w), pipe or filter",
+            Self::Pipe => "Pipe the range, or the view, to a shell command (:pipe)",
+            Self::ToggleFollow => "Toggle following new entries",
+            Self::ToggleMouse => "Release the mouse for text selection",
+            Self::Clear => "Clear all entries",
+            Self::Stats => "Summarize the range, or the view: levels, busiest minute, top messages",
+            Self::ToggleDedup => "Collapse repeated entries into one row (:dedup)",
+            Self::Filter => "Filter the entries with a query like level>=warn, or to the range",
+            Self::Edit => "Open the file in $VISUAL or $EDITOR at the selected line",
+            Self::CommandLine => "Open the command line (:open, :goto, :filter, :set, :export)",
+            Self::Help => "Show this help",
+            Self::Back => "Close the open pane, or quit",
+            Self::Quit => "Quit logz",
+        }
+    }
+
+    /// Find the action with the given config name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// `KeyPress` is a single key with its modifiers, normalized so that it can be
+/// compared against terminal key events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPress {
+    /// `code` is the key that was pressed.
+    code: KeyCode,
+    /// `modifiers` are the modifier keys held down, without shift for characters.
+    modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    /// Create a normalized `KeyPress`. Shift is already part of an uppercase
+    /// character, so it is dropped for characters.
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match code {
+            KeyCode::Char(c) => {
+                modifiers.remove(KeyModifiers::SHIFT);
+                if modifiers.contains(KeyModifiers::CONTROL) {
+                    KeyCode::Char(c.to_ascii_lowercase())
+                } else {
+                    KeyCode::Char(c)
+                }
+            }
+            KeyCode::BackTab => {
+                modifiers.insert(KeyModifiers::SHIFT);
+                KeyCode::Tab
+            }
+            code => code,
+        };
+        Self { code, modifiers }
+    }
+
+    /// Parse a single key name like `Down`, `C-d` or `lt` as used inside `<...>`.
+    fn parse_named(name: &str) -> Result<Self, String> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = name;
+        while let Some((prefix, tail)) = rest.split_once('-').filter(|(_, tail)| !tail.is_empty()) {
+            match prefix.to_ascii_uppercase().as_str() {
+                "C" => modifiers |= KeyModifiers::CONTROL,
+                "A" | "M" => modifiers |= KeyModifiers::ALT,
+                "S" => modifiers |= KeyModifiers::SHIFT,
+                _ => break,
+            }
+            rest = tail;
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "enter" | "cr" | "return" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "bs" | "backspace" => KeyCode::Backspace,
+                "del" | "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "space" => KeyCode::Char(' '),
+                "lt" => KeyCode::Char('<'),
+                "gt" => KeyCode::Char('>'),
+                function if function.starts_with('f') => function[1..]
+                    .parse()
+                    .ok()
+                    .filter(|n| (1..=12).contains(n))
+                    .map(KeyCode::F)
+                    .ok_or_else(|| format!("unknown key <{name}>"))?,
+                _ => return Err(format!("unknown key <{name}>")),
+            },
+        };
+
+        Ok(Self::new(code, modifiers))
+    }
+
+    /// Parse a key sequence in vim notation, e.g. `gg`, `<C-d>` or `<C-x><C-c>`.
+    pub fn parse_sequence(text: &str) -> Result<Vec<Self>, String> {
+        let mut keys = Vec::new();
+        let mut rest = text;
+
+        while let Some(c) = rest.chars().next() {
+            if c == '<'
+                && let Some(end) = rest.find('>').filter(|&end| end > 1)
+            {
+                keys.push(Self::parse_named(&rest[1..end])?);
+                rest = &rest[end + 1..];
+            } else {
+                keys.push(Self::new(KeyCode::Char(c), KeyModifiers::NONE));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+
+        if keys.is_empty() {
+            return Err("empty key binding".into());
+        }
+        Ok(keys)
+    }
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(key: KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self.code {
+            KeyCode::Char(' ') => "Space".to_owned(),
+            KeyCode::Char('<') => "lt".to_owned(),
+            KeyCode::Char(c) if self.modifiers.is_empty() => return write!(f, "{c}"),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::F(n) => format!("F{n}"),
+            KeyCode::Enter => "Enter".to_owned(),
+            KeyCode::Backspace => "BS".to_owned(),
+            code => format!("{code:?}"),
+        };
+
+        write!(f, "<")?;
+        for (modifier, prefix) in [
+            (KeyModifiers::CONTROL, "C-"),
+            (KeyModifiers::ALT, "A-"),
+            (KeyModifiers::SHIFT, "S-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{prefix}")?;
+            }
+        }
+        write!(f, "{name}>")
+    }
+}
+
+/// `KeyMatch` is the result of feeding a key into the `KeyMap`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyMatch {
+    /// The keys so far complete a binding.
+    Action(Action),
+    /// The keys so far are the start of at least one binding.
+    Pending,
+    /// No binding starts with the keys so far.
+    None,
+}
+
+/// `KeyMap` binds key sequences to actions.
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    /// `bindings` are the key sequences with their action, in the order they were
+    /// configured so that they are listed the same way.
+    bindings: Vec<(Vec<KeyPress>, Action)>,
+}
+
+impl KeyMap {
+    /// `vim` is the default preset.
+    fn vim() -> Vec<(Action, &'static [&'static str])> {
+        vec![
+            (Action::ScrollDown, &["j", "<Down>"]),
+            (Action::ScrollUp, &["k", "<Up>"]),
+            (Action::PageDown, &["<PageDown>"]),
+            (Action::PageUp, &["<PageUp>"]),
+            (Action::HalfPageDown, &["<C-d>"]),
+            (Action::HalfPageUp, &["<C-u>"]),
+            (Action::GoToTop, &["gg", "<Home>"]),
+            (Action::GoToBottom, &["G", "<End>"]),
+            (Action::SelectNext, &["J", "<S-Down>"]),
+            (Action::SelectPrevious, &["K", "<S-Up>"]),
+            (Action::NextError, &["]e"]),
+            (Action::PreviousError, &["[e"]),
+            (Action::ToggleDetail, &["<Enter>"]),
+            (Action::ToggleFold, &["za", "<Tab>"]),
+            (Action::ToggleAllFolds, &["zA", "<S-Tab>"]),
+            (Action::ToggleTable, &["t"]),
+            (Action::Columns, &["C"]),
+            (Action::ToggleBookmark, &["b"]),
+            (Action::Bookmarks, &["B"]),
+            (Action::SetMark, &["m"]),
+            (Action::JumpToMark, &["'", "`"]),
+            (Action::Annotate, &["a"]),
+            (Action::Yank, &["yy"]),
+            (Action::YankField, &["yf"]),
+            (Action::VisualLine, &["V"]),
+            (Action::Pipe, &["!"]),
+            (Action::ToggleFollow, &["f"]),
+            (Action::ToggleMouse, &["M"]),
+            (Action::Clear, &["c"]),
+            (Action::Stats, &["S"]),
+            (Action::ToggleDedup, &["D"]),
+            (Action::Filter, &["&"]),
+            (Action::Edit, &["v"]),
+            (Action::CommandLine, &[":"]),
+            (Action::Help, &["?", "<F1>"]),
+            (Action::Back, &["<Esc>"]),
+            (Action::Quit, &["q"]),
+        ]
+    }
+
+    /// `emacs` is a preset mixing emacs and less keys.
+    fn emacs() -> Vec<(Action, &'static [&'static str])> {
+        vec![
+            (Action::ScrollDown, &["<C-n>", "e", "<Down>"]),
+            (Action::ScrollUp, &["<C-p>", "y", "<Up>"]),
+            (Action::PageDown, &["<C-v>", "<Space>", "f", "<PageDown>"]),
+            (Action::PageUp, &["<A-v>", "b", "<PageUp>"]),
+            (Action::HalfPageDown, &["d"]),
+            (Action::HalfPageUp, &["u"]),
+            (Action::GoToTop, &["<A-lt>", "g", "<lt>", "<Home>"]),
+            (Action::GoToBottom, &["<A-gt>", "G", ">", "<End>"]),
+            (Action::SelectNext, &["<S-Down>"]),
+            (Action::SelectPrevious, &["<S-Up>"]),
+            (Action::NextError, &["<A-n>", "]e"]),
+            (Action::PreviousError, &["<A-p>", "[e"]),
+            (Action::ToggleDetail, &["<Enter>"]),
+            (Action::ToggleFold, &["<Tab>"]),
+            (Action::ToggleAllFolds, &["<S-Tab>"]),
+            (Action::ToggleTable, &["<A-t>"]),
+            (Action::Columns, &["<A-c>"]),
+            (Action::ToggleBookmark, &["<A-b>"]),
+            (Action::Bookmarks, &["<A-B>"]),
+            (Action::SetMark, &["m"]),
+            (Action::JumpToMark, &["'"]),
+            (Action::Annotate, &["<A-a>"]),
+            (Action::Yank, &["<A-w>"]),
+            (Action::YankField, &["<A-W>"]),
+            (Action::VisualLine, &["<C-Space>"]),
+            (Action::Pipe, &["<A-|>"]),
+            (Action::ToggleFollow, &["F"]),
+            (Action::ToggleMouse, &["<A-m>"]),
+            (Action::Clear, &["<C-l>"]),
+            (Action::Stats, &["<A-s>"]),
+            (Action::ToggleDedup, &["<A-d>"]),
+            (Action::Filter, &["&"]),
+            (Action::Edit, &["v"]),
+            (Action::CommandLine, &[":", "<A-x>"]),
+            (Action::Help, &["?", "<F1>"]),
+            (Action::Back, &["<Esc>", "<C-g>"]),
+            (Action::Quit, &["q", "<C-x><C-c>"]),
+        ]
+    }
+
+    /// Build the keymap from the `[keys]` config section, starting from its preset.
+    /// Fails on unknown actions, unparsable keys and conflicting bindings.
+    pub fn from_config(config: &KeysConfig) -> Result<Self, String> {
+        let preset = match config.preset.as_deref() {
+            None | Some("vim") => Self::vim(),
+            Some("emacs" | "less") => Self::emacs(),
+            Some(other) => return Err(format!("unknown keymap preset '{other}'")),
+        };
+
+        let mut bound: BTreeMap<Action, Vec<String>> = preset
+            .into_iter()
+            .map(|(action, keys)| (action, keys.iter().map(|&k| k.to_owned()).collect()))
+            .collect();
+
+        for (name, keys) in &config.bindings {
+            let action =
+                Action::from_name(name).ok_or_else(|| format!("unknown action '{name}'"))?;
+            bound.insert(action, keys.clone());
+        }
+
+        let mut bindings: Vec<(Vec<KeyPress>, Action)> = Vec::new();
+        for (action, keys) in bound {
+            for text in keys {
+                let sequence = KeyPress::parse_sequence(&text)
+                    .map_err(|e| format!("{}: {e}", action.name()))?;
+                match bindings.iter().find(|(bound, _)| *bound == sequence) {
+                    Some((_, other)) if *other != action => {
+                        return Err(format!(
+                            "'{text}' is bound to both {} and {}",
+                            other.name(),
+                            action.name()
+                        ));
+                    }
+                    Some(_) => {}
+                    None => bindings.push((sequence, action)),
+                }
+            }
+        }
+
+        let keymap = Self { bindings };
+        keymap.check_prefixes()?;
+        Ok(keymap)
+    }
+
+    /// Make sure no binding is the start of another one, which would make the
+    /// longer binding unreachable.
+    fn check_prefixes(&self) -> Result<(), String> {
+        for (sequence, action) in &self.bindings {
+            for (prefix, other) in &self.bindings {
+                if prefix.len() < sequence.len() && sequence.starts_with(prefix) {
+                    return Err(format!(
+                        "'{}' ({}) hides '{}' ({})",
+                        Self::describe(prefix),
+                        other.name(),
+                        Self::describe(sequence),
+                        action.name()
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Look up the keys typed so far.
+    pub fn lookup(&self, keys: &[KeyPress]) -> KeyMatch {
+        if let Some((_, action)) = self.bindings.iter().find(|(sequence, _)| sequence == keys) {
+            return KeyMatch::Action(*action);
+        }
+
+        if self
+            .bindings
+            .iter()
+            .any(|(sequence, _)| sequence.starts_with(keys))
+        {
+            KeyMatch::Pending
+        } else {
+            KeyMatch::None
+        }
+    }
+
+    /// Check if the keys typed so far are the start of a binding of `action`.
+    pub fn starts(&self, keys: &[KeyPress], action: Action) -> bool {
+        self.bindings
+            .iter()
+            .any(|(sequence, bound)| *bound == action && sequence.starts_with(keys))
+    }
+
+    /// All key sequences bound to `action`, formatted for display.
+    pub fn keys_for(&self, action: Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(sequence, _)| Self::describe(sequence))
+            .collect()
+    }
+
+    /// The title of a popup, `title`, with a hint naming the first key bound to one of
+    /// `actions` that close it, like `Help - <Esc> to close`.
+    pub fn closing_title(&self, title: &str, actions: &[Action]) -> String {
+        match actions
+            .iter()
+            .find_map(|&action| self.keys_for(action).into_iter().next())
+        {
+            Some(key) => format!("{title} - {key} to close"),
+            None => title.to_owned(),
+        }
+    }
+
+    /// Format a key sequence in the same notation it is configured with.
+    pub fn describe(sequence: &[KeyPress]) -> String {
+        sequence.iter().map(ToString::to_string).collect()
+    }
+}
@@ -0,0 +1,120 @@
+//! `output` holds the popup showing what a shell command printed.
+
+use super::{
+    TAB,
+    keymap::{Action, KeyMap},
+    theme::Theme,
+};
+use crate::ansi;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Flex, Layout, Margin, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState},
+};
+
+/// `OutputPanel` is the popup showing the output of a command the entries were piped
+/// to, scrolled like the help overlay.
+#[derive(Debug, Clone)]
+pub struct OutputPanel {
+    /// `title` names the command and how it exited.
+    title: String,
+    /// `lines` are the lines of the output.
+    lines: Vec<String>,
+    /// `scroll` is the number of lines scrolled past.
+    scroll: usize,
+    /// `viewport` is the number of lines that fit in the popup on the last render.
+    viewport: usize,
+}
+
+impl OutputPanel {
+    /// Create an `OutputPanel` titled `title` showing `output`.
+    pub fn new(title: String, output: &str) -> Self {
+        Self {
+            title,
+            lines: output.lines().map(|line| line.replace('\t', TAB)).collect(),
+            scroll: 0,
+            viewport: 1,
+        }
+    }
+
+    /// Handle a key typed while the panel is open and return whether it is still open.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let page = self.viewport.max(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => return false,
+            KeyCode::Char('g') if control => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.scroll += 1,
+            KeyCode::Char('n') if control => self.scroll += 1,
+            KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Char('p') if control => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.scroll += page,
+            KeyCode::Char('d' | 'f') if control => self.scroll += page,
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(page),
+            KeyCode::Char('u' | 'b') if control => self.scroll = self.scroll.saturating_sub(page),
+            KeyCode::Home | KeyCode::Char('g') => self.scroll = 0,
+            KeyCode::End | KeyCode::Char('G') => self.scroll = usize::MAX,
+            _ => {}
+        }
+        true
+    }
+
+    /// Scroll the output by `delta` lines, up when negative.
+    pub const fn scroll_by(&mut self, delta: isize) {
+        self.scroll = self.scroll.saturating_add_signed(delta);
+    }
+
+    /// Draw the panel centered on top of `area`, keeping the scroll offset within
+    /// the output.
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, keymap: &KeyMap, theme: &Theme) {
+        let [popup] = Layout::horizontal([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [popup] = Layout::vertical([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(popup);
+
+        self.viewport = popup.height.saturating_sub(2) as usize;
+        let max_scroll = self.lines.len().saturating_sub(self.viewport);
+        self.scroll = self.scroll.min(max_scroll);
+
+        // Colored output keeps its colors
+        let lines: Vec<Line> = if self.lines.is_empty() {
+            vec![Line::from(Span::styled("no output", theme.hint))]
+        } else {
+            self.lines
+                .iter()
+                .skip(self.scroll)
+                .take(self.viewport)
+                .map(|line| Line::from(ansi::spans(line, theme.text)))
+                .collect()
+        };
+
+        let output = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.border)
+                    .title(keymap.closing_title(&self.title, &[Action::Back])),
+            )
+            .style(theme.text);
+        frame.render_widget(Clear, popup);
+        frame.render_widget(output, popup);
+
+        if max_scroll > 0 {
+            let mut scrollbar_state = ScrollbarState::new(max_scroll + 1).position(self.scroll);
+            frame.render_stateful_widget(
+                Scrollbar::default()
+                    .orientation(ScrollbarOrientation::VerticalRight)
+                    .style(theme.scrollbar),
+                popup.inner(Margin {
+                    vertical: 1,
+                    horizontal: 0,
+                }),
+                &mut scrollbar_state,
+            );
+        }
+    }
+}
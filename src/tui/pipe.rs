@@ -0,0 +1,96 @@
+//! `pipe` runs the shell commands entries are piped to without blocking the `Tui`.
+
+use crate::event::AppEvent;
+use std::{
+    io::Write,
+    process::{self, Stdio},
+    sync::mpsc::Sender,
+    thread,
+};
+
+/// `Pipe` is a shell command the entries were piped to that is still running. It
+/// sends `AppEvent::PipeDone` to the main loop once it exits, and is stopped when
+/// dropped.
+#[derive(Debug)]
+pub struct Pipe {
+    /// `command` is the shell command as it was typed.
+    pub command: String,
+    /// `id` is the process id of the shell, which leads the process group of the
+    /// command.
+    id: u32,
+    /// `done` is set once the command exited, so that its process group is left alone.
+    done: bool,
+}
+
+impl Pipe {
+    /// Run `command` in the shell with `input` on its standard input. What it writes
+    /// to its standard output and error, and how it exited, is sent to `events`.
+    pub fn spawn(command: &str, input: String, events: Sender<AppEvent>) -> Result<Self, String> {
+        let mut shell = process::Command::new("sh");
+        shell
+            .args(["-c", command])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        // A process group of its own lets `stop` reach every command of a pipeline
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut shell, 0);
+        let mut child = shell.spawn().map_err(|e| e.to_string())?;
+        let id = child.id();
+
+        // Writing from another thread keeps a command that answers before reading
+        // all of its input from blocking both sides
+        let mut stdin = child.stdin.take().ok_or("no standard input")?;
+        thread::spawn(move || {
+            // A command that doesn't read all of its input closes the pipe early
+            let _ = stdin.write_all(input.as_bytes());
+        });
+        thread::spawn(move || {
+            let event = match child.wait_with_output() {
+                Ok(output) => {
+                    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+                    text.push_str(&String::from_utf8_lossy(&output.stderr));
+                    AppEvent::PipeDone(Ok((text, output.status)))
+                }
+                Err(e) => AppEvent::PipeDone(Err(e.to_string())),
+            };
+            let _ = events.send(event);
+        });
+
+        Ok(Self {
+            command: command.to_owned(),
+            id,
+            done: false,
+        })
+    }
+
+    /// Mark the command as exited, once its `AppEvent::PipeDone` arrived.
+    pub const fn finish(&mut self) {
+        self.done = true;
+    }
+
+    /// Stop the command and everything it started. Its `AppEvent::PipeDone` still
+    /// arrives, with what it wrote until then.
+    #[cfg(unix)]
+    pub fn stop(&self) -> Result<(), String> {
+        use nix::{sys::signal, unistd::Pid};
+
+        let group = i32::try_from(self.id).map_err(|e| e.to_string())?;
+        signal::killpg(Pid::from_raw(group), signal::Signal::SIGKILL).map_err(|e| e.to_string())
+    }
+
+    /// Stop the command and everything it started. Only supported on Unix.
+    #[cfg(not(unix))]
+    pub fn stop(&self) -> Result<(), String> {
+        Err("stopping commands is not supported on this platform".to_owned())
+    }
+}
+
+impl Drop for Pipe {
+    /// Commands like `tail -f` would otherwise keep running after the `Tui` is gone.
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.stop();
+        }
+    }
+}
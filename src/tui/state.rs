@@ -0,0 +1,1086 @@
+//! `state` holds the view state of the `Tui` that is independent of the terminal.
+
+use super::table;
+use crate::{
+    ansi,
+    export::{self, ExportFormat},
+    filter::{Filter, compare_values},
+    log_entry::{LogEntry, LogLevel},
+    stats, timestamp,
+};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs, io,
+    ops::{Range, RangeInclusive},
+    time::{Duration, Instant},
+};
+
+/// `PAGE_SIZE` is how many rows `PageUp` and `PageDown` move.
+const PAGE_SIZE: usize = 10;
+
+/// `BOOKMARK_SYMBOL` marks bookmarked entries in the gutter.
+pub const BOOKMARK_SYMBOL: char = '●';
+
+/// `MESSAGE_DURATION` is how long a `StatusMessage` stays in the footer.
+const MESSAGE_DURATION: Duration = Duration::from_secs(5);
+
+/// `StatusMessage` is feedback shown in the footer in place of the status line.
+#[derive(Debug, Clone)]
+pub struct StatusMessage {
+    /// `text` is the message itself.
+    pub text: String,
+    /// `is_error` shows the message as an error.
+    pub is_error: bool,
+    /// `shown_at` is when the message appeared.
+    shown_at: Instant,
+}
+
+impl StatusMessage {
+    /// Create a new `StatusMessage` shown from now on.
+    pub fn new(text: String, is_error: bool) -> Self {
+        Self {
+            text,
+            is_error,
+            shown_at: Instant::now(),
+        }
+    }
+
+    /// Check if the message has been shown long enough.
+    pub fn is_expired(&self) -> bool {
+        self.shown_at.elapsed() >= MESSAGE_DURATION
+    }
+}
+
+/// `Dedup` is how consecutive entries of the view are found to repeat each other, to
+/// collapse them into one row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dedup {
+    /// `Exact` collapses entries with the same content, apart from their time field or
+    /// the time a plain text entry starts with.
+    Exact,
+    /// `Similar` collapses entries that only differ in their numbers, UUIDs, IDs and
+    /// times.
+    Similar,
+}
+
+impl Dedup {
+    /// The text entries are compared by.
+    fn key(self, entry: &LogEntry) -> Cow<'_, str> {
+        if self == Self::Similar {
+            return Cow::Owned(stats::normalize(&entry.content));
+        }
+        // Repeated entries are written at different times
+        let time = entry
+            .time_field()
+            .and_then(|name| entry.fields.iter().find(|field| field.name == name))
+            .and_then(|field| field.value_range.clone())
+            .filter(|range| entry.content.get(range.clone()).is_some());
+        match time {
+            Some(range) => {
+                let mut content = entry.content.clone();
+                content.replace_range(range, "");
+                Cow::Owned(content)
+            }
+            None => Cow::Borrowed(
+                timestamp::leading(&entry.content).map_or(&entry.content, |(_, rest)| rest),
+            ),
+        }
+    }
+}
+
+/// `TuiState` is everything the `Tui` draws, separated from the terminal so that the
+/// draw functions can borrow it while the terminal is borrowed mutably.
+///
+/// Scrolling and selection work on rows of the view, which are the entries that pass
+/// the current filter, in line order unless the view is sorted. `visible` maps each
+/// row to its index in `log_entries`. With `dedup`, entries repeating the one before
+/// them are collapsed into the row of the first.
+#[derive(Debug)]
+pub struct TuiState {
+    /// `log_entries` are the log entries that will be displayed to the screen.
+    pub log_entries: Vec<LogEntry>,
+    /// `visible` are the indices into `log_entries` of the rows in the view.
+    pub visible: Vec<usize>,
+    /// `filter` hides the entries that don't match it.
+    pub filter: Option<Filter>,
+    /// `scroll_offset` is the amount of offset that the screen has to scroll to show the correct
+    /// log entries.
+    pub scroll_offset: usize,
+    /// `selected_index` is the row of the view that's highlighted in the UI.
+    pub selected_index: Option<usize>,
+    /// `reveal_selection` scrolls the selection into view on the next draw, after it
+    /// moved. Scrolling leaves it off screen.
+    pub reveal_selection: bool,
+    /// `auto_scroll` keeps the window at the bottom of the log file when true.
+    pub auto_scroll: bool,
+    /// `viewport_height` is the number of screen rows available for entries in the
+    /// list, updated on every render.
+    pub viewport_height: usize,
+    /// `row_map` maps each screen row of the list onto the view row drawn there,
+    /// updated on every render.
+    pub row_map: Vec<usize>,
+    /// `show_detail` opens the detail pane for the selected entry.
+    pub show_detail: bool,
+    /// `mouse_captured` is true while the terminal sends mouse events to the `Tui`.
+    /// When false, the terminal handles the mouse and native text selection works.
+    pub mouse_captured: bool,
+    /// `help_scroll` is the scroll offset of the help overlay, which is open when set.
+    pub help_scroll: Option<usize>,
+    /// `file_name` is the name of the log file shown in the header.
+    pub file_name: String,
+    /// `format_name` is the name of the format of the log file, shown in the status line.
+    pub format_name: String,
+    /// `wrap` wraps long entries onto several rows.
+    pub wrap: bool,
+    /// `line_numbers` shows the line number in front of each entry.
+    pub line_numbers: bool,
+    /// `ansi` draws entries with the colors of their escape sequences instead of
+    /// plain text.
+    pub ansi: bool,
+    /// `expanded` are the indices into `log_entries` of the multiline entries that
+    /// show all of their lines. Other multiline entries only show the first one.
+    pub expanded: HashSet<usize>,
+    /// `table` shows the entries as a table with a column per field.
+    pub table: bool,
+    /// `columns` are the fields shown as columns in the table view.
+    pub columns: Vec<String>,
+    /// `column_widths` are the widths of the `columns`, grown to fit the values drawn.
+    pub column_widths: Vec<usize>,
+    /// `sort` is the field the view is sorted by and whether it is sorted descending.
+    /// Following new entries is off while the view is sorted.
+    pub sort: Option<(String, bool)>,
+    /// `bookmarks` are the first lines of the bookmarked entries.
+    pub bookmarks: BTreeSet<usize>,
+    /// `marks` maps the letters of the marks onto the first line of the entry each is
+    /// set on.
+    pub marks: BTreeMap<char, usize>,
+    /// `notes` maps the first line of the entries with a note onto the note.
+    pub notes: BTreeMap<usize, String>,
+    /// `error_level` is the lowest level of the entries the error motions jump to and
+    /// the status line counts.
+    pub error_level: LogLevel,
+    /// `visual_anchor` is the first line of the entry the visual range was started on,
+    /// so that it stays put when entries are added. The range runs from it to the
+    /// selection.
+    pub visual_anchor: Option<usize>,
+    /// `dedup` collapses consecutive entries of the view repeating each other.
+    pub dedup: Option<Dedup>,
+    /// `duplicates` maps the index of the first entry of each run of repeated entries
+    /// onto the indices of all entries of the run.
+    pub duplicates: HashMap<usize, Vec<usize>>,
+    /// `run_heads` maps the index of every entry in `duplicates` onto the index of the
+    /// first entry of its run.
+    run_heads: HashMap<usize, usize>,
+    /// `expanded_duplicates` are the first lines of the runs of repeated entries that
+    /// show all of their entries.
+    pub expanded_duplicates: HashSet<usize>,
+    /// `message` is feedback from the last command, shown in the footer.
+    pub message: Option<StatusMessage>,
+}
+
+impl TuiState {
+    /// Create a new `TuiState` for a list that fits `viewport_height` rows.
+    pub fn new(viewport_height: usize) -> Self {
+        Self {
+            log_entries: Vec::new(),
+            visible: Vec::new(),
+            filter: None,
+            scroll_offset: 0,
+            selected_index: None,
+            reveal_selection: false,
+            auto_scroll: true,
+            viewport_height,
+            row_map: Vec::new(),
+            show_detail: false,
+            mouse_captured: true,
+            help_scroll: None,
+            file_name: String::new(),
+            format_name: String::new(),
+            wrap: false,
+            line_numbers: false,
+            ansi: true,
+            expanded: HashSet::new(),
+            table: false,
+            columns: Vec::new(),
+            column_widths: Vec::new(),
+            sort: None,
+            bookmarks: BTreeSet::new(),
+            marks: BTreeMap::new(),
+            notes: BTreeMap::new(),
+            error_level: LogLevel::Warn,
+            visual_anchor: None,
+            dedup: None,
+            duplicates: HashMap::new(),
+            run_heads: HashMap::new(),
+            expanded_duplicates: HashSet::new(),
+            message: None,
+        }
+    }
+
+    /// `max_scroll` is the largest scroll offset that still keeps the screen full.
+    pub const fn max_scroll(&self) -> usize {
+        self.visible.len().saturating_sub(self.viewport_height)
+    }
+
+    /// Check if the user is currently viewing the bottom of the log
+    pub const fn is_at_bottom(&self) -> bool {
+        self.scroll_offset >= self.max_scroll()
+    }
+
+    /// Scroll to show the latest entries (keeps screen full)
+    pub const fn scroll_to_bottom(&mut self) {
+        self.scroll_offset = self.max_scroll();
+    }
+
+    /// Scroll the view by `delta` rows without moving the selection.
+    pub fn scroll_by(&mut self, delta: isize) {
+        self.scroll_offset = self
+            .scroll_offset
+            .saturating_add_signed(delta)
+            .min(self.max_scroll());
+        // Scrolling up pauses following, scrolling back to the bottom resumes it
+        self.auto_scroll = delta > 0 && self.is_at_bottom() && self.sort.is_none();
+    }
+
+    /// Scroll so that the row at `position` of `track_height` rows on the scrollbar
+    /// is in view.
+    pub fn seek(&mut self, position: usize, track_height: usize) {
+        let max_scroll = self.max_scroll();
+        let last = track_height.saturating_sub(1).max(1);
+        self.scroll_offset = (position.min(last) * max_scroll).div_ceil(last);
+        self.auto_scroll = self.is_at_bottom() && self.sort.is_none();
+    }
+
+    /// Select the view row at `index`, scrolling just enough to keep it visible.
+    pub fn select(&mut self, index: usize) {
+        if self.visible.is_empty() {
+            return;
+        }
+
+        let index = index.min(self.visible.len() - 1);
+        self.selected_index = Some(index);
+        self.reveal_selection = true;
+
+        if index < self.scroll_offset {
+            self.scroll_offset = index;
+        } else if index >= self.scroll_offset + self.viewport_height {
+            self.scroll_offset = (index + 1).saturating_sub(self.viewport_height);
+        }
+
+        // Selecting the last entry follows the log, anything else pauses it
+        self.auto_scroll = index + 1 == self.visible.len() && self.sort.is_none();
+    }
+
+    /// Move the selection by `delta` entries. Without a selection, the top visible
+    /// entry is selected first.
+    pub fn move_selection(&mut self, delta: isize) {
+        match self.selected_index {
+            Some(index) => self.select(index.saturating_add_signed(delta)),
+            None => self.select(self.scroll_offset),
+        }
+    }
+
+    /// Scroll the view down by a page.
+    pub fn page_down(&mut self) {
+        self.scroll_by(PAGE_SIZE.cast_signed());
+    }
+
+    /// Scroll the view up by a page.
+    pub fn page_up(&mut self) {
+        self.scroll_by(-PAGE_SIZE.cast_signed());
+    }
+
+    /// Scroll to the first entry, pausing following.
+    pub const fn scroll_to_top(&mut self) {
+        self.scroll_offset = 0;
+        self.auto_scroll = false;
+    }
+
+    /// Scroll to the last entry and follow new entries, unless the view is sorted.
+    pub fn scroll_to_end(&mut self) {
+        self.scroll_to_bottom();
+        self.auto_scroll = self.sort.is_none();
+    }
+
+    /// Toggle auto-follow mode
+    pub fn toggle_follow(&mut self) {
+        self.set_follow(!self.auto_scroll);
+    }
+
+    /// Turn auto-follow mode on or off. Following puts a sorted view back in line order.
+    pub fn set_follow(&mut self, follow: bool) {
+        if follow && self.sort.is_some() {
+            self.set_sort(None);
+        }
+        self.auto_scroll = follow;
+        if self.auto_scroll {
+            self.scroll_to_bottom();
+        }
+    }
+
+    /// The view row drawn at `row` of the list (relative to the first entry row), if any.
+    pub fn entry_at_row(&self, row: usize) -> Option<usize> {
+        self.row_map.get(row).copied()
+    }
+
+    /// The currently selected entry.
+    pub fn selected_entry(&self) -> Option<&LogEntry> {
+        self.selected_index
+            .and_then(|row| self.visible.get(row))
+            .and_then(|&i| self.log_entries.get(i))
+    }
+
+    /// Check if the entry passes the current filter.
+    fn is_visible(&self, entry: &LogEntry) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| filter.matches(entry))
+    }
+
+    /// Set or clear the filter and rebuild the view, keeping the selected entry
+    /// selected when it is still visible.
+    pub fn set_filter(&mut self, filter: Option<Filter>) {
+        let selected = self
+            .selected_index
+            .and_then(|row| self.visible.get(row).copied());
+        self.filter = filter;
+        self.visible = (0..self.log_entries.len())
+            .filter(|&i| self.is_visible(&self.log_entries[i]))
+            .collect();
+        self.sort_view();
+        self.dedup_view();
+        if self
+            .visual_anchor
+            .is_some_and(|anchor| self.row_of_line(anchor).is_none())
+        {
+            self.visual_anchor = None;
+        }
+
+        self.selected_index = None;
+        self.scroll_offset = self.scroll_offset.min(self.max_scroll());
+        if let Some(selected) = selected {
+            // Select the same entry, or the nearest one after it
+            let row = if self.sort.is_some() {
+                self.visible
+                    .iter()
+                    .position(|&i| self.is_in_run(i, selected))
+                    .unwrap_or(self.visible.len())
+            } else {
+                // A collapsed entry selects the row of its run
+                let row = self.visible.partition_point(|&i| i <= selected);
+                match row.checked_sub(1) {
+                    Some(run) if self.is_in_run(self.visible[run], selected) => run,
+                    _ => row,
+                }
+            };
+            if row < self.visible.len() {
+                self.select(row);
+            }
+        }
+        if self.auto_scroll {
+            self.scroll_to_bottom();
+        }
+    }
+
+    /// Sort the view by the `sort` field. Entries without the field go last, and
+    /// entries with the same value stay in line order.
+    fn sort_view(&mut self) {
+        let Some((field, descending)) = &self.sort else {
+            return;
+        };
+        let entries = &self.log_entries;
+        self.visible.sort_by(|&a, &b| {
+            let (a, b) = (&entries[a], &entries[b]);
+            // Levels sort by severity rather than by name
+            let ordering = if field == "level" {
+                match (a.level, b.level) {
+                    (Some(a), Some(b)) => a.cmp(&b),
+                    (Some(_), None) => return Ordering::Less,
+                    (None, Some(_)) => return Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                }
+            } else {
+                match (a.field(field), b.field(field)) {
+                    (Some(a), Some(b)) => compare_values(a, b),
+                    (Some(_), None) => return Ordering::Less,
+                    (None, Some(_)) => return Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                }
+            };
+            if *descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+
+    /// Collapse the runs of entries in the view repeating each other into the row of
+    /// their first entry, unless the run is expanded.
+    fn dedup_view(&mut self) {
+        self.duplicates.clear();
+        self.run_heads.clear();
+        let Some(dedup) = self.dedup else {
+            return;
+        };
+
+        let mut rows = Vec::with_capacity(self.visible.len());
+        let mut run: Vec<usize> = Vec::new();
+        for index in std::mem::take(&mut self.visible) {
+            let repeats = run.first().is_some_and(|&first| {
+                dedup.key(&self.log_entries[first]) == dedup.key(&self.log_entries[index])
+            });
+            if !repeats {
+                self.push_run(&mut rows, std::mem::take(&mut run));
+            }
+            run.push(index);
+        }
+        self.push_run(&mut rows, run);
+        self.visible = rows;
+    }
+
+    /// Add the rows of a `run` of repeated entries to `rows`: the first entry, or all
+    /// of them when the run is expanded.
+    fn push_run(&mut self, rows: &mut Vec<usize>, run: Vec<usize>) {
+        let Some(&first) = run.first() else {
+            return;
+        };
+        if run.len() == 1 {
+            rows.push(first);
+            return;
+        }
+        if self.is_run_expanded(first) {
+            rows.extend(&run);
+        } else {
+            rows.push(first);
+        }
+        self.run_heads
+            .extend(run.iter().map(|&index| (index, first)));
+        self.duplicates.insert(first, run);
+    }
+
+    /// Check if the run of repeated entries starting at `index` shows all of its entries.
+    pub fn is_run_expanded(&self, index: usize) -> bool {
+        self.expanded_duplicates
+            .contains(&self.log_entries[index].line)
+    }
+
+    /// Check if the entry at `index` belongs to the run starting at `first`.
+    fn is_in_run(&self, first: usize, index: usize) -> bool {
+        first == index || self.run_heads.get(&index) == Some(&first)
+    }
+
+    /// The first entry of the run of repeated entries the entry at `index` belongs to.
+    fn run_of(&self, index: usize) -> Option<usize> {
+        self.run_heads.get(&index).copied()
+    }
+
+    /// The badge of the collapsed run of repeated entries starting at `index`, like
+    /// ` ×342 10:00:01–10:05:33` with the times of its first and last entry.
+    pub fn duplicate_badge(&self, index: usize) -> Option<String> {
+        let run = self.duplicates.get(&index)?;
+        if self.is_run_expanded(index) {
+            return None;
+        }
+        let mut badge = format!(" ×{}", run.len());
+        let first = self.log_entries[index].timestamp;
+        let last = run.last().and_then(|&i| self.log_entries[i].timestamp);
+        if let (Some(first), Some(last)) = (first, last) {
+            let format = if first.date_naive() == last.date_naive() {
+                "%H:%M:%S"
+            } else {
+                "%Y-%m-%d %H:%M:%S"
+            };
+            badge.push_str(&format!(
+                " {}–{}",
+                first.format(format),
+                last.format(format)
+            ));
+        }
+        Some(badge)
+    }
+
+    /// Collapse repeated entries with `dedup`, or show every entry when `None`.
+    pub fn set_dedup(&mut self, dedup: Option<Dedup>) {
+        self.dedup = dedup;
+        self.expanded_duplicates.clear();
+        self.set_filter(self.filter.clone());
+    }
+
+    /// The number of entries hidden in collapsed runs of repeated entries.
+    pub fn hidden_duplicates(&self) -> usize {
+        self.duplicates
+            .iter()
+            .filter(|&(&first, _)| !self.is_run_expanded(first))
+            .map(|(_, run)| run.len() - 1)
+            .sum()
+    }
+
+    /// Expand the collapsed run of repeated entries at the selection. Returns the
+    /// number of entries shown now, or `None` when the selection is no collapsed run.
+    pub fn expand_selected_run(&mut self) -> Option<usize> {
+        let index = *self.visible.get(self.selected_index?)?;
+        let count = self.duplicates.get(&index)?.len();
+        if self.is_run_expanded(index) {
+            return None;
+        }
+        self.expanded_duplicates
+            .insert(self.log_entries[index].line);
+        self.set_filter(self.filter.clone());
+        Some(count)
+    }
+
+    /// Expand or collapse the run of repeated entries the selected entry belongs to,
+    /// selecting its first entry when it is collapsed. Returns whether it is expanded
+    /// now, or `None` when the selected entry repeats no other one.
+    pub fn toggle_selected_run(&mut self) -> Option<bool> {
+        let index = *self.visible.get(self.selected_index?)?;
+        let first = self.run_of(index)?;
+        let line = self.log_entries[first].line;
+        let expanded = self.expanded_duplicates.insert(line);
+        if !expanded {
+            self.expanded_duplicates.remove(&line);
+            self.selected_index = self.visible.iter().position(|&i| i == first);
+        }
+        self.set_filter(self.filter.clone());
+        Some(expanded)
+    }
+
+    /// Sort the view by a field, or put it back in line order when `None`. Sorting
+    /// stops following new entries.
+    pub fn set_sort(&mut self, sort: Option<(String, bool)>) {
+        if sort.is_some() {
+            self.auto_scroll = false;
+        }
+        self.sort = sort;
+        self.set_filter(self.filter.clone());
+    }
+
+    /// Sort the view by `field`, switching from ascending to descending to line order
+    /// when it is already sorted by it.
+    pub fn cycle_sort(&mut self, field: String) {
+        let sort = match &self.sort {
+            Some((sorted, false)) if *sorted == field => Some((field, true)),
+            Some((sorted, true)) if *sorted == field => None,
+            _ => Some((field, false)),
+        };
+        self.set_sort(sort);
+    }
+
+    /// Set the fields shown as columns in the table view.
+    pub fn set_columns(&mut self, columns: Vec<String>) {
+        self.columns = columns;
+        self.column_widths.clear();
+    }
+
+    /// Select the entry containing the 1-based line number `line`, or the first one
+    /// after it.
+    pub fn goto_line(&mut self, line: usize) -> Result<(), String> {
+        let target = line.saturating_sub(1);
+        let row = if self.sort.is_some() {
+            self.visible
+                .iter()
+                .enumerate()
+                .filter(|&(_, &i)| self.log_entries[i].last_line() >= target)
+                .min_by_key(|&(_, &i)| self.log_entries[i].line)
+                .map_or(self.visible.len(), |(row, _)| row)
+        } else {
+            self.visible
+                .partition_point(|&i| self.log_entries[i].last_line() < target)
+        };
+        if row >= self.visible.len() {
+            return Err(format!("line {line} is past the end of the view"));
+        }
+        self.select(row);
+        Ok(())
+    }
+
+    /// Select the first entry at or after the time typed in `text`, a full time or a
+    /// time of day on the day of the selected entry.
+    pub fn goto_time(&mut self, text: &str) -> Result<(), String> {
+        let reference = self
+            .selected_index
+            .into_iter()
+            .chain(0..self.visible.len())
+            .find_map(|row| self.log_entries[self.visible[row]].timestamp);
+        if reference.is_none() {
+            return Err("time: no entry has a time that could be read".into());
+        }
+        let target = timestamp::parse_target(text, reference)
+            .ok_or_else(|| format!("time: '{text}' is not a time"))?;
+
+        let row = self
+            .visible
+            .iter()
+            .position(|&i| {
+                self.log_entries[i]
+                    .timestamp
+                    .is_some_and(|time| time >= target)
+            })
+            .ok_or_else(|| format!("time: no entry at or after {text}"))?;
+        self.select(row);
+        Ok(())
+    }
+
+    /// Bookmark the selected entry, or remove its bookmark. Returns whether the entry
+    /// is bookmarked now.
+    pub fn toggle_bookmark(&mut self) -> Result<bool, String> {
+        let line = self.selected_entry().ok_or("no entry selected")?.line;
+        if self.bookmarks.remove(&line) {
+            return Ok(false);
+        }
+        self.bookmarks.insert(line);
+        Ok(true)
+    }
+
+    /// Set the mark `name` on the selected entry, moving it there when it was set on
+    /// another one.
+    pub fn set_mark(&mut self, name: char) -> Result<(), String> {
+        let line = self.selected_entry().ok_or("no entry selected")?.line;
+        self.marks.insert(name, line);
+        Ok(())
+    }
+
+    /// Select the entry the mark `name` is set on.
+    pub fn jump_to_mark(&mut self, name: char) -> Result<(), String> {
+        let line = *self
+            .marks
+            .get(&name)
+            .ok_or_else(|| format!("mark '{name}' is not set"))?;
+        self.select_line(line)
+    }
+
+    /// Remove the bookmark and the marks of the entry starting at `line`.
+    pub fn remove_marks(&mut self, line: usize) {
+        self.bookmarks.remove(&line);
+        self.marks.retain(|_, &mut marked| marked != line);
+    }
+
+    /// The symbol in the gutter of the entry starting at `line`: the letter of a mark
+    /// set on it, or the bookmark symbol.
+    pub fn mark_symbol(&self, line: usize) -> Option<char> {
+        self.marks
+            .iter()
+            .find(|&(_, &marked)| marked == line)
+            .map(|(&name, _)| name)
+            .or_else(|| self.bookmarks.contains(&line).then_some(BOOKMARK_SYMBOL))
+    }
+
+    /// Set the note of the selected entry, or remove it when `note` is `None`. Returns
+    /// the line number of the entry, counting from 1.
+    pub fn set_note(&mut self, note: Option<String>) -> Result<usize, String> {
+        let line = self.selected_entry().ok_or("no entry selected")?.line;
+        match note {
+            Some(note) => self.notes.insert(line, note),
+            None => self.notes.remove(&line),
+        };
+        Ok(line + 1)
+    }
+
+    /// The note of the selected entry, if it has one.
+    pub fn selected_note(&self) -> Option<&str> {
+        let line = self.selected_entry()?.line;
+        self.notes.get(&line).map(String::as_str)
+    }
+
+    /// The entry starting at `line`, whether or not it is in the view.
+    pub fn entry_at_line(&self, line: usize) -> Option<&LogEntry> {
+        self.log_entries
+            .binary_search_by_key(&line, |entry| entry.line)
+            .ok()
+            .map(|i| &self.log_entries[i])
+    }
+
+    /// Select the entry starting at `line`.
+    pub fn select_line(&mut self, line: usize) -> Result<(), String> {
+        let row = self
+            .row_of_line(line)
+            .ok_or_else(|| format!("line {} is not in the view", line + 1))?;
+        self.select(row);
+        Ok(())
+    }
+
+    /// The view row of the entry starting at `line`, if it is in the view.
+    fn row_of_line(&self, line: usize) -> Option<usize> {
+        if self.sort.is_some() {
+            self.visible
+                .iter()
+                .position(|&i| self.log_entries[i].line == line)
+        } else {
+            self.visible
+                .binary_search_by_key(&line, |&i| self.log_entries[i].line)
+                .ok()
+        }
+    }
+
+    /// Start a visual range on the selected entry, or end the one started. Returns
+    /// whether a range is being selected now.
+    pub fn toggle_visual(&mut self) -> Result<bool, String> {
+        if self.visual_anchor.take().is_some() {
+            return Ok(false);
+        }
+        let line = self.selected_entry().ok_or("no entry selected")?.line;
+        self.visual_anchor = Some(line);
+        Ok(true)
+    }
+
+    /// The view rows of the visual range, from its start to the selection.
+    pub fn visual_rows(&self) -> Option<RangeInclusive<usize>> {
+        let anchor = self.row_of_line(self.visual_anchor?)?;
+        let selected = self.selected_index?;
+        Some(anchor.min(selected)..=anchor.max(selected))
+    }
+
+    /// The entries of the visual range, or all entries in the view without one.
+    pub fn range_entries(&self) -> impl Iterator<Item = &LogEntry> {
+        let rows = self
+            .visual_rows()
+            .map_or(0..self.visible.len(), |rows| *rows.start()..*rows.end() + 1);
+        self.row_entries(rows)
+    }
+
+    /// The entries of the view `rows`, with all entries of their collapsed runs.
+    fn row_entries(&self, rows: Range<usize>) -> impl Iterator<Item = &LogEntry> {
+        // Collapsed runs of repeated entries stand for all of their entries
+        self.visible[rows]
+            .iter()
+            .flat_map(|i| match self.duplicates.get(i) {
+                Some(run) if !self.is_run_expanded(*i) => run.as_slice(),
+                _ => std::slice::from_ref(i),
+            })
+            .map(|&i| &self.log_entries[i])
+    }
+
+    /// The first and last line of the entries of the visual range, with every entry
+    /// of its collapsed runs, counting from 1. In a sorted view, the lines of the range
+    /// may include entries outside it.
+    pub fn visual_lines(&self) -> Option<(usize, usize)> {
+        let rows = self.visual_rows()?;
+        self.row_entries(*rows.start()..*rows.end() + 1)
+            .map(|entry| entry.line + 1)
+            .fold(None, |range, line| match range {
+                Some((first, last)) => Some((line.min(first), line.max(last))),
+                None => Some((line, line)),
+            })
+    }
+
+    /// The entries of the visual range, or of the view, as the input of a command they
+    /// are piped to, without escape sequences.
+    pub fn pipe_input(&self) -> String {
+        self.range_entries()
+            .map(|entry| format!("{}\n", ansi::strip(&entry.content)))
+            .collect()
+    }
+
+    /// The text of the entries of the visual range, or of the selected row, with every
+    /// entry of its collapsed runs and without escape sequences. Returns it with the
+    /// number of entries.
+    pub fn yank_text(&self) -> Result<(String, usize), String> {
+        let rows = self
+            .visual_rows()
+            .map(|rows| *rows.start()..*rows.end() + 1)
+            .or_else(|| self.selected_index.map(|row| row..row + 1))
+            .ok_or("no entry selected")?;
+        let text: Vec<_> = self
+            .row_entries(rows)
+            .map(|entry| ansi::strip(&entry.content))
+            .collect();
+        Ok((text.join("\n"), text.len()))
+    }
+
+    /// The value of the field `name` of the selected entry.
+    pub fn selected_field(&self, name: &str) -> Result<&str, String> {
+        let entry = self.selected_entry().ok_or("no entry selected")?;
+        entry
+            .field(name)
+            .ok_or_else(|| format!("line {} has no field '{name}'", entry.line + 1))
+    }
+
+    /// Select the next row after the selection whose entry passes `predicate`, or the
+    /// previous one when `forward` is false, wrapping around at the ends. Without a
+    /// selection, it starts at the top of the screen. Returns whether it
+    /// wrapped, or `None` when no entry passes.
+    fn select_next(
+        &mut self,
+        forward: bool,
+        predicate: impl Fn(&LogEntry) -> bool,
+    ) -> Option<bool> {
+        let len = self.visible.len();
+        if len == 0 {
+            return None;
+        }
+
+        let start = self.selected_index.unwrap_or(if forward {
+            self.scroll_offset.wrapping_sub(1)
+        } else {
+            self.scroll_offset
+        });
+        let row = (1..=len)
+            .map(|step| {
+                if forward {
+                    start.wrapping_add(step) % len
+                } else {
+                    (start + len * 2 - step) % len
+                }
+            })
+            .find(|&row| predicate(&self.log_entries[self.visible[row]]))?;
+        self.select(row);
+        Some(if forward {
+            start < len && row <= start
+        } else {
+            row >= start
+        })
+    }
+
+    /// Select the next entry at the `error_level` or above, or the previous one when
+    /// `forward` is false, wrapping around at the ends. Returns whether it wrapped.
+    pub fn jump_to_error(&mut self, forward: bool) -> Result<bool, String> {
+        let error_level = self.error_level;
+        self.select_next(forward, |entry| is_at_least(entry, error_level))
+            .ok_or_else(|| {
+                let level = error_level.to_string().to_lowercase();
+                format!("no {level} entries or worse in the view")
+            })
+    }
+
+    /// The number of entries at the `error_level` or above after the selection (or
+    /// from the top of the screen without one), and in the whole view.
+    pub fn error_counts(&self) -> (usize, usize) {
+        let start = self
+            .selected_index
+            .map_or(self.scroll_offset, |row| row + 1);
+        self.visible
+            .iter()
+            .enumerate()
+            .filter(|&(_, &i)| is_at_least(&self.log_entries[i], self.error_level))
+            .fold((0, 0), |(remaining, total), (row, _)| {
+                (remaining + usize::from(row >= start), total + 1)
+            })
+    }
+
+    /// Check if the entry at `index` in `log_entries` shows all of its lines.
+    pub fn is_expanded(&self, index: usize) -> bool {
+        self.expanded.contains(&index)
+    }
+
+    /// Expand or collapse the selected entry when it spans several lines.
+    pub fn toggle_fold(&mut self) -> Result<(), String> {
+        let index = self
+            .selected_index
+            .and_then(|row| self.visible.get(row).copied())
+            .ok_or("no entry selected")?;
+        if self.log_entries[index].line_count() == 1 {
+            return Err("the entry has a single line".into());
+        }
+
+        if !self.expanded.remove(&index) {
+            self.expanded.insert(index);
+        }
+        Ok(())
+    }
+
+    /// Collapse every entry when any is expanded, otherwise expand all of them.
+    pub fn toggle_all_folds(&mut self) {
+        if self.expanded.is_empty() {
+            self.expanded = (0..self.log_entries.len())
+                .filter(|&i| self.log_entries[i].line_count() > 1)
+                .collect();
+        } else {
+            self.expanded.clear();
+        }
+    }
+
+    /// Write the entries of the visual range, or of the view, to `path` in `format`,
+    /// or in the format of its extension. CSV files get the columns of the table view.
+    /// Returns the number of entries written.
+    pub fn export(&self, path: &str, format: Option<ExportFormat>) -> Result<usize, String> {
+        let format = format.unwrap_or_else(|| ExportFormat::from_path(path));
+        let entries: Vec<&LogEntry> = self.range_entries().collect();
+        let columns = if self.columns.is_empty() {
+            table::default_columns(&self.log_entries)
+        } else {
+            self.columns.clone()
+        };
+        let columns = export::csv_columns(&columns, &entries);
+        let title = match &self.filter {
+            Some(filter) => format!("{} ({filter})", self.file_name),
+            None => self.file_name.clone(),
+        };
+
+        let path = shellexpand::full(path).map_err(|e| e.to_string())?;
+        let file = fs::File::create(path.as_ref()).map_err(|e| e.to_string())?;
+        export::write(
+            &mut io::BufWriter::new(file),
+            format,
+            &entries,
+            &columns,
+            &title,
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(entries.len())
+    }
+
+    /// Write the entries with notes to `path` as a Markdown incident timeline, in line
+    /// order with their time, note and content. Returns the number of notes written.
+    pub fn export_timeline(&self, path: &str) -> Result<usize, String> {
+        if self.notes.is_empty() {
+            return Err("no entries have notes".into());
+        }
+        let path = shellexpand::full(path).map_err(|e| e.to_string())?;
+        fs::write(path.as_ref(), self.timeline()).map_err(|e| e.to_string())?;
+        Ok(self.notes.len())
+    }
+
+    /// The Markdown incident timeline of the entries with notes.
+    fn timeline(&self) -> String {
+        let mut markdown = format!("# Incident timeline\n\n`{}`\n", self.file_name);
+        for (&line, note) in &self.notes {
+            let entry = self.entry_at_line(line);
+            let heading = match entry.and_then(|entry| entry.timestamp) {
+                Some(time) => format!(
+                    "{} (line {})",
+                    time.format("%Y-%m-%d %H:%M:%S %:z"),
+                    line + 1
+                ),
+                None => format!("Line {}", line + 1),
+            };
+            markdown.push_str(&format!("\n## {heading}\n\n{note}\n"));
+            if let Some(entry) = entry {
+                // The fence has to be longer than any run of backticks in the entry
+                let mut fence = "```".to_owned();
+                while entry.content.contains(&fence) {
+                    fence.push('`');
+                }
+                markdown.push_str(&format!("\n{fence}\n{}\n{fence}\n", entry.content));
+            }
+        }
+        markdown
+    }
+
+    /// Set all log entries (replaces existing entries)
+    pub fn set_log_entries(&mut self, entries: Vec<LogEntry>) {
+        self.log_entries = entries;
+        self.expanded.clear();
+        self.expanded_duplicates.clear();
+        self.column_widths.clear();
+        self.visual_anchor = None;
+        // Don't auto-scroll on initial load, let user see from the beginning
+        self.auto_scroll = false;
+        self.scroll_offset = 0;
+        self.selected_index = None;
+        self.set_filter(self.filter.clone());
+    }
+
+    /// Add new log entries (for when the source only provides new entries)
+    pub fn append_new_log_entries(&mut self, new_entries: Vec<LogEntry>) {
+        // Check if we're at the bottom AND auto-scroll is enabled
+        let should_auto_scroll = self.auto_scroll && self.is_at_bottom();
+        let was_on_last = self.selected_index.is_some()
+            && self.selected_index == self.visible.len().checked_sub(1);
+
+        // Entries starting at or after the first new one are replaced, since the log
+        // file hands out the last entry again when lines were added to it
+        if let Some(first) = new_entries.first() {
+            while self
+                .log_entries
+                .last()
+                .is_some_and(|entry| entry.line >= first.line)
+            {
+                self.log_entries.pop();
+                let index = self.log_entries.len();
+                if self.visible.last() == Some(&index) {
+                    self.visible.pop();
+                }
+                self.remove_from_last_run(index);
+            }
+        }
+
+        // A sorted view doesn't follow, the new entries only need sorting in
+        if self.sort.is_some() {
+            self.visible.retain(|&i| i < self.log_entries.len());
+            self.log_entries.extend(new_entries);
+            self.set_filter(self.filter.clone());
+            return;
+        }
+
+        // Add the new entries, and the ones passing the filter to the view
+        for entry in new_entries {
+            let index = self.log_entries.len();
+            let visible = self.is_visible(&entry);
+            self.log_entries.push(entry);
+            if visible && !self.add_to_last_run(index) {
+                self.visible.push(index);
+            }
+        }
+
+        // Only auto-scroll if both conditions are met:
+        // 1. User was already at the bottom
+        // 2. Auto-scroll mode is enabled (not paused)
+        if should_auto_scroll {
+            self.scroll_to_bottom();
+            if was_on_last {
+                self.selected_index = Some(self.visible.len() - 1);
+            }
+        }
+        if self.selected_index >= Some(self.visible.len()) {
+            self.selected_index = self.visible.len().checked_sub(1);
+        }
+    }
+
+    /// Add the new entry at `index` to the run of repeated entries of the last row,
+    /// when it repeats it. Returns whether it was added and needs no row of its own.
+    fn add_to_last_run(&mut self, index: usize) -> bool {
+        let (Some(dedup), Some(&last)) = (self.dedup, self.visible.last()) else {
+            return false;
+        };
+        let first = self.run_of(last).unwrap_or(last);
+        if dedup.key(&self.log_entries[first]) != dedup.key(&self.log_entries[index]) {
+            return false;
+        }
+        self.duplicates
+            .entry(first)
+            .or_insert_with(|| vec![first])
+            .push(index);
+        self.run_heads.insert(first, first);
+        self.run_heads.insert(index, first);
+        // Entries of an expanded run get rows of their own
+        !self.is_run_expanded(first)
+    }
+
+    /// Take the entry at `index`, which was the last one, out of the run of repeated
+    /// entries it ended.
+    fn remove_from_last_run(&mut self, index: usize) {
+        let Some(first) = self.run_of(index) else {
+            return;
+        };
+        self.run_heads.remove(&index);
+        let run = self.duplicates.entry(first).or_default();
+        run.retain(|&i| i != index);
+        if first == index || run.len() < 2 {
+            for i in self.duplicates.remove(&first).unwrap_or_default() {
+                self.run_heads.remove(&i);
+            }
+        }
+    }
+
+    /// Clear all log entries
+    pub fn clear_log_entries(&mut self) {
+        self.log_entries.clear();
+        self.visible.clear();
+        self.expanded.clear();
+        self.duplicates.clear();
+        self.run_heads.clear();
+        self.expanded_duplicates.clear();
+        self.visual_anchor = None;
+        self.scroll_offset = 0;
+        self.selected_index = None;
+        self.auto_scroll = true; // Re-enable auto-scroll after clearing
+    }
+}
+
+/// Check if `entry` has a level of at least `level`.
+fn is_at_least(entry: &LogEntry, level: LogLevel) -> bool {
+    entry.level.is_some_and(|entry_level| entry_level >= level)
+}
@@ -0,0 +1,315 @@
+//! `table` shows structured entries as a table with a column per field, and holds the
+//! picker that chooses the columns.
+
+use super::{TAB, theme::Theme};
+use crate::log_entry::LogEntry;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    Frame,
+    layout::{Constraint, Flex, Layout, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+};
+
+/// `REST_COLUMN` is the header of the trailing column, which holds the fields without
+/// a column of their own.
+pub const REST_COLUMN: &str = "…";
+
+/// `MAX_COLUMN_WIDTH` is the widest a column gets. Longer values are cut off.
+const MAX_COLUMN_WIDTH: usize = 40;
+
+/// `COLUMN_GAP` separates two columns.
+const COLUMN_GAP: &str = "  ";
+
+/// `PICKER_HINT` explains the keys of the column picker below its list.
+const PICKER_HINT: &str = "Space toggle  J/K move  s sort  Esc done";
+
+/// The value of the column `name` for `entry`, on a single line. The `level` column
+/// falls back to the detected level of entries without a level field.
+fn cell(entry: &LogEntry, name: &str) -> String {
+    match entry.field(name) {
+        Some(value) => value.replace('\n', " ").replace('\t', TAB),
+        None if name == "level" => entry
+            .level
+            .map(|level| level.to_string().to_lowercase())
+            .unwrap_or_default(),
+        None => String::new(),
+    }
+}
+
+/// The value of the trailing column for `entry`: the fields without a column as
+/// `name=value` pairs, or the first line of the content for entries without fields.
+fn rest(entry: &LogEntry, columns: &[String]) -> String {
+    if entry.fields.is_empty() {
+        let first = entry.content.lines().next().unwrap_or_default();
+        return first.replace('\t', TAB);
+    }
+    entry
+        .fields
+        .iter()
+        .filter(|field| !columns.contains(&field.name))
+        .map(|field| format!("{}={}", field.name, field.value.replace('\n', " ")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The cells of `entry`, one for each of the `columns` followed by the trailing column.
+pub fn cells(entry: &LogEntry, columns: &[String]) -> Vec<String> {
+    columns
+        .iter()
+        .map(|name| cell(entry, name))
+        .chain(std::iter::once(rest(entry, columns)))
+        .collect()
+}
+
+/// Widen `widths`, one for each column before the trailing one, to fit `cells`.
+/// Columns only ever grow, so that they don't jump around while scrolling.
+pub fn fit_widths(widths: &mut Vec<usize>, columns: &[String], cells: &[String]) {
+    widths.resize(columns.len(), 0);
+    for ((width, name), cell) in widths.iter_mut().zip(columns).zip(cells) {
+        // Room for the sort marker next to the name
+        let wanted = cell.chars().count().max(name.chars().count() + 2);
+        *width = (*width).max(wanted.min(MAX_COLUMN_WIDTH));
+    }
+}
+
+/// `text` padded or cut off to exactly `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return format!("{text:<width$}");
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+/// The spans of a table row with `cells` laid out in columns of `widths`.
+pub fn row(cells: &[String], widths: &[usize], style: Style) -> Vec<Span<'static>> {
+    let mut text = String::new();
+    for (cell, &width) in cells.iter().zip(widths) {
+        text.push_str(&fit(cell, width));
+        text.push_str(COLUMN_GAP);
+    }
+    if let Some(last) = cells.get(widths.len()) {
+        text.push_str(last);
+    }
+    vec![Span::styled(text, style)]
+}
+
+/// The header of the table, indented by `indent` columns. The column the view is
+/// sorted by is marked with the direction of the sort.
+pub fn header(
+    columns: &[String],
+    widths: &[usize],
+    sort: Option<(&str, bool)>,
+    indent: usize,
+    theme: &Theme,
+) -> Line<'static> {
+    let mut text = " ".repeat(indent);
+    for (name, &width) in columns.iter().zip(widths) {
+        let marker = match sort {
+            Some((sorted, descending)) if sorted == name => {
+                if descending {
+                    " ▼"
+                } else {
+                    " ▲"
+                }
+            }
+            _ => "",
+        };
+        text.push_str(&fit(&format!("{name}{marker}"), width));
+        text.push_str(COLUMN_GAP);
+    }
+    text.push_str(REST_COLUMN);
+    Line::from(Span::styled(text, theme.column_header))
+}
+
+/// The columns shown when none were chosen: the time, level and message of the first
+/// structured entry, or its first fields when it has none of them. Entries without
+/// fields get a level column next to their content.
+pub fn default_columns(entries: &[LogEntry]) -> Vec<String> {
+    let Some(entry) = entries.iter().find(|entry| !entry.fields.is_empty()) else {
+        return vec!["level".to_owned()];
+    };
+
+    let mut columns: Vec<String> = [entry.time_field(), Some("level"), entry.message_field()]
+        .into_iter()
+        .flatten()
+        .filter(|&name| name != "level" || entry.level.is_some())
+        .map(str::to_owned)
+        .collect();
+    if columns.len() < 2 {
+        let others: Vec<String> = entry
+            .fields
+            .iter()
+            .map(|field| field.name.clone())
+            .filter(|name| !columns.contains(name))
+            .take(3)
+            .collect();
+        columns.extend(others);
+    }
+    columns
+}
+
+/// The names of every field in `entries`, in the order they first appear, starting
+/// with `level` when any entry has a level.
+pub fn known_fields(entries: &[LogEntry]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    if entries.iter().any(|entry| entry.level.is_some()) {
+        names.push("level".to_owned());
+    }
+    for field in entries.iter().flat_map(|entry| &entry.fields) {
+        if !names.contains(&field.name) {
+            names.push(field.name.clone());
+        }
+    }
+    names
+}
+
+/// `PickerResult` is the outcome of a key typed into the `ColumnPicker`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PickerResult {
+    /// The picker is still open.
+    Open,
+    /// The view should be sorted by the field, or sorted the other way when it already is.
+    Sort(String),
+    /// The picker was closed.
+    Closed,
+}
+
+/// `ColumnPicker` is the popup that chooses which fields are columns and in which
+/// order, and which column the view is sorted by.
+#[derive(Debug, Clone)]
+pub struct ColumnPicker {
+    /// `fields` are the known fields in column order, with whether each is a column.
+    fields: Vec<(String, bool)>,
+    /// `selected` is the index of the highlighted field.
+    selected: usize,
+}
+
+impl ColumnPicker {
+    /// Create a `ColumnPicker` listing the `columns` first, followed by the other
+    /// `known` fields.
+    pub fn new(columns: &[String], known: Vec<String>) -> Self {
+        let mut fields: Vec<(String, bool)> =
+            columns.iter().map(|name| (name.clone(), true)).collect();
+        fields.extend(
+            known
+                .into_iter()
+                .filter(|name| !columns.contains(name))
+                .map(|name| (name, false)),
+        );
+        Self {
+            fields,
+            selected: 0,
+        }
+    }
+
+    /// `columns` are the fields chosen as columns, in order.
+    pub fn columns(&self) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter(|(_, shown)| *shown)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Handle a key typed while the picker is open.
+    pub fn handle_key(&mut self, key: KeyEvent) -> PickerResult {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        let last = self.fields.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => return PickerResult::Closed,
+            KeyCode::Char('c' | 'g') if control => return PickerResult::Closed,
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1).min(last),
+            KeyCode::Char('n') if control => self.selected = (self.selected + 1).min(last),
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char('p') if control => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Char(' ' | 'x') | KeyCode::Enter => {
+                if let Some((_, shown)) = self.fields.get_mut(self.selected) {
+                    *shown = !*shown;
+                }
+            }
+            KeyCode::Char('J') if self.selected < last => {
+                self.fields.swap(self.selected, self.selected + 1);
+                self.selected += 1;
+            }
+            KeyCode::Char('K') if self.selected > 0 => {
+                self.fields.swap(self.selected, self.selected - 1);
+                self.selected -= 1;
+            }
+            KeyCode::Char('s') => {
+                if let Some((name, _)) = self.fields.get(self.selected) {
+                    return PickerResult::Sort(name.clone());
+                }
+            }
+            _ => {}
+        }
+        PickerResult::Open
+    }
+
+    /// Draw the picker centered on top of `area`, with the column the view is sorted
+    /// by marked.
+    pub fn draw(&self, frame: &mut Frame, area: Rect, sort: Option<(&str, bool)>, theme: &Theme) {
+        let mut lines: Vec<Line> = self
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(i, (name, shown))| {
+                let check = if *shown { "[x]" } else { "[ ]" };
+                let marker = match sort {
+                    Some((sorted, true)) if sorted == name => " ▼",
+                    Some((sorted, false)) if sorted == name => " ▲",
+                    _ => "",
+                };
+                let style = if i == self.selected {
+                    theme.text.patch(theme.selected)
+                } else {
+                    theme.text
+                };
+                Line::from(Span::styled(format!(" {check} {name}{marker} "), style))
+            })
+            .collect();
+        if lines.is_empty() {
+            lines.push(Line::from(Span::styled(" no fields ", theme.hint)));
+        }
+
+        let width = lines
+            .iter()
+            .map(Line::width)
+            .chain(std::iter::once(PICKER_HINT.len() + 2))
+            .max()
+            .unwrap_or_default()
+            + 2;
+        let [popup] =
+            Layout::horizontal([Constraint::Length(width.try_into().unwrap_or(u16::MAX))])
+                .flex(Flex::Center)
+                .areas(area);
+        let height = (lines.len() + 4).min(area.height as usize);
+        let [popup] = Layout::vertical([Constraint::Length(height.try_into().unwrap_or(u16::MAX))])
+            .flex(Flex::Center)
+            .areas(popup);
+
+        // Keep the highlighted field in view above the hint
+        let viewport = height.saturating_sub(4).max(1);
+        let scroll = self.selected.saturating_sub(viewport - 1);
+        let mut visible: Vec<Line> = lines.into_iter().skip(scroll).take(viewport).collect();
+        visible.push(Line::default());
+        visible.push(Line::from(Span::styled(
+            format!(" {PICKER_HINT}"),
+            theme.hint,
+        )));
+
+        let picker = Paragraph::new(visible)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(theme.border)
+                    .title("Columns"),
+            )
+            .style(theme.text);
+        frame.render_widget(Clear, popup);
+        frame.render_widget(picker, popup);
+    }
+}
@@ -0,0 +1,478 @@
+//! `theme` holds the colors the `Tui` draws with and adapts them to what the
+//! terminal supports.
+
+use crate::{
+    config::{StyleConfig, ThemeConfig},
+    log_entry::LogLevel,
+};
+use ratatui::style::{Color, Modifier, Style};
+use std::{env, str::FromStr};
+
+/// `ANSI_COLORS` are the 16 basic terminal colors in index order, with the RGB values
+/// xterm uses for them by default.
+const ANSI_COLORS: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// `CUBE_LEVELS` are the channel values of the 6x6x6 color cube of the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// `ColorDepth` is how many colors the terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// `None` draws without any color, only bold, reversed and the like.
+    None,
+    /// `Ansi16` is the 16 basic colors every terminal has.
+    Ansi16,
+    /// `Ansi256` is the 256-color palette.
+    Ansi256,
+    /// `TrueColor` is any RGB color.
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Parse a color depth from the config file, `None` meaning detect it.
+    fn parse(name: &str) -> Result<Option<Self>, String> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Ok(None),
+            "none" | "off" => Ok(Some(Self::None)),
+            "16" | "ansi" => Ok(Some(Self::Ansi16)),
+            "256" => Ok(Some(Self::Ansi256)),
+            "truecolor" | "24bit" => Ok(Some(Self::TrueColor)),
+            other => Err(format!("unknown color support '{other}'")),
+        }
+    }
+
+    /// Detect the color depth from the environment, honoring `NO_COLOR`.
+    fn detect() -> Self {
+        if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            return Self::None;
+        }
+        if env::var("COLORTERM").is_ok_and(|value| value == "truecolor" || value == "24bit") {
+            return Self::TrueColor;
+        }
+        match env::var("TERM") {
+            Ok(term) if term == "dumb" => Self::None,
+            Ok(term) if term.contains("256color") => Self::Ansi256,
+            _ => Self::Ansi16,
+        }
+    }
+
+    /// Convert the colors of `style` to the closest colors the terminal can show.
+    fn adapt(self, mut style: Style) -> Style {
+        style.fg = style.fg.map(|color| self.convert(color));
+        style.bg = style.bg.map(|color| self.convert(color));
+        style
+    }
+
+    /// Convert `color` to the closest color the terminal can show.
+    fn convert(self, color: Color) -> Color {
+        match (self, color) {
+            (Self::None, _) => Color::Reset,
+            (Self::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(nearest_indexed((r, g, b))),
+            (Self::Ansi16, Color::Indexed(index)) if index < 16 => ANSI_COLORS[index as usize].0,
+            (Self::Ansi16, Color::Rgb(..) | Color::Indexed(_)) => {
+                let rgb = to_rgb(color);
+                ANSI_COLORS
+                    .into_iter()
+                    .min_by_key(|&(_, ansi)| distance(rgb, ansi))
+                    .map_or(color, |(ansi, _)| ansi)
+            }
+            _ => color,
+        }
+    }
+}
+
+/// The RGB value of an indexed or RGB color.
+fn to_rgb(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(index @ 0..16) => ANSI_COLORS[index as usize].1,
+        Color::Indexed(index @ 16..232) => {
+            let index = index - 16;
+            (
+                CUBE_LEVELS[(index / 36) as usize],
+                CUBE_LEVELS[(index / 6 % 6) as usize],
+                CUBE_LEVELS[(index % 6) as usize],
+            )
+        }
+        Color::Indexed(index) => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+        _ => (0, 0, 0),
+    }
+}
+
+/// The squared distance between two RGB values.
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let channel = |x: u8, y: u8| u32::from(x.abs_diff(y)).pow(2);
+    channel(a.0, b.0) + channel(a.1, b.1) + channel(a.2, b.2)
+}
+
+/// The index of the color cube or grayscale ramp entry closest to `rgb`.
+fn nearest_indexed(rgb: (u8, u8, u8)) -> u8 {
+    let level = |value: u8| match value {
+        0..48 => 0,
+        48..115 => 1,
+        _ => (value - 35) / 40,
+    };
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+
+    let average = (u16::from(rgb.0) + u16::from(rgb.1) + u16::from(rgb.2)) / 3;
+    let gray = 232 + u8::try_from(average.saturating_sub(3) / 10).map_or(23, |step| step.min(23));
+
+    if distance(rgb, to_rgb(Color::Indexed(gray))) < distance(rgb, to_rgb(Color::Indexed(cube))) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// `Theme` is the style of every element the `Tui` draws.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// `header` is the line with the file name at the top.
+    pub header: Style,
+    /// `border` is the borders around the panes.
+    pub border: Style,
+    /// `text` is the log entries and the detail pane.
+    pub text: Style,
+    /// `selected` is patched over the selected entry.
+    pub selected: Style,
+    /// `line_number` is the line number gutter.
+    pub line_number: Style,
+    /// `mark` is the bookmark and mark symbols in the gutter.
+    pub mark: Style,
+    /// `note` is the row with the note of an entry below it.
+    pub note: Style,
+    /// `status` is the status line in the footer.
+    pub status: Style,
+    /// `info` is feedback from a command in the footer.
+    pub info: Style,
+    /// `error` is errors in the footer.
+    pub error: Style,
+    /// `prompt` is the command line input.
+    pub prompt: Style,
+    /// `hint` is the completion hint next to the prompt.
+    pub hint: Style,
+    /// `field_key` is the keys of structured fields in entries.
+    pub field_key: Style,
+    /// `field_value` is the values of structured fields in entries, other than the
+    /// level and message which keep the style of the entry.
+    pub field_value: Style,
+    /// `column_header` is the names of the columns in the table view.
+    pub column_header: Style,
+    /// `help_heading` is the category headings of the help overlay.
+    pub help_heading: Style,
+    /// `help_key` is the keys in the help overlay.
+    pub help_key: Style,
+    /// `scrollbar` is the scrollbars of the list and the help overlay.
+    pub scrollbar: Style,
+    /// `levels` are patched over entries of each `LogLevel`, in `LogLevel::ALL` order.
+    levels: [Style; 6],
+    /// `depth` is the color support of the terminal the theme was adapted to.
+    depth: ColorDepth,
+}
+
+impl Theme {
+    /// `NAMES` are the built-in themes.
+    pub const NAMES: [&str; 3] = ["dark", "light", "high-contrast"];
+
+    /// `dark` is the default theme, for terminals with a dark background.
+    pub fn dark() -> Self {
+        Self {
+            header: Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            border: Style::new(),
+            text: Style::new().fg(Color::White),
+            selected: Style::new()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+            line_number: Style::new().fg(Color::DarkGray),
+            mark: Style::new().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+            note: Style::new()
+                .fg(Color::Gray)
+                .add_modifier(Modifier::DIM | Modifier::ITALIC),
+            status: Style::new().fg(Color::Yellow),
+            info: Style::new().fg(Color::Green),
+            error: Style::new().fg(Color::Red),
+            prompt: Style::new().fg(Color::White),
+            hint: Style::new().fg(Color::DarkGray),
+            field_key: Style::new().add_modifier(Modifier::DIM),
+            field_value: Style::new().fg(Color::Cyan),
+            column_header: Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            help_heading: Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            help_key: Style::new().fg(Color::Yellow),
+            scrollbar: Style::new(),
+            levels: [
+                Style::new().fg(Color::DarkGray),
+                Style::new().fg(Color::Gray),
+                Style::new(),
+                Style::new().fg(Color::Yellow),
+                Style::new().fg(Color::LightRed),
+                Style::new().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ],
+            depth: ColorDepth::TrueColor,
+        }
+    }
+
+    /// `light` is for terminals with a light background.
+    pub fn light() -> Self {
+        Self {
+            header: Style::new()
+                .fg(Color::Rgb(0x00, 0x5f, 0x87))
+                .add_modifier(Modifier::BOLD),
+            border: Style::new().fg(Color::Rgb(0x80, 0x80, 0x80)),
+            text: Style::new().fg(Color::Black),
+            selected: Style::new()
+                .bg(Color::Rgb(0xd0, 0xd0, 0xd0))
+                .add_modifier(Modifier::BOLD),
+            line_number: Style::new().fg(Color::Rgb(0x80, 0x80, 0x80)),
+            mark: Style::new()
+                .fg(Color::Rgb(0x87, 0x00, 0x87))
+                .add_modifier(Modifier::BOLD),
+            note: Style::new()
+                .fg(Color::Rgb(0x60, 0x60, 0x60))
+                .add_modifier(Modifier::ITALIC),
+            status: Style::new().fg(Color::Rgb(0x5f, 0x00, 0x87)),
+            info: Style::new().fg(Color::Rgb(0x00, 0x80, 0x00)),
+            error: Style::new().fg(Color::Rgb(0xc0, 0x00, 0x00)),
+            prompt: Style::new().fg(Color::Black),
+            hint: Style::new().fg(Color::Rgb(0x80, 0x80, 0x80)),
+            field_key: Style::new().fg(Color::Rgb(0x80, 0x80, 0x80)),
+            field_value: Style::new().fg(Color::Rgb(0x00, 0x5f, 0x87)),
+            column_header: Style::new()
+                .fg(Color::Rgb(0x00, 0x5f, 0x87))
+                .add_modifier(Modifier::BOLD),
+            help_heading: Style::new()
+                .fg(Color::Rgb(0x00, 0x5f, 0x87))
+                .add_modifier(Modifier::BOLD),
+            help_key: Style::new().fg(Color::Rgb(0x87, 0x5f, 0x00)),
+            scrollbar: Style::new().fg(Color::Rgb(0x80, 0x80, 0x80)),
+            levels: [
+                Style::new().fg(Color::Rgb(0xa0, 0xa0, 0xa0)),
+                Style::new().fg(Color::Rgb(0x60, 0x60, 0x60)),
+                Style::new(),
+                Style::new().fg(Color::Rgb(0xaf, 0x5f, 0x00)),
+                Style::new().fg(Color::Rgb(0xc0, 0x00, 0x00)),
+                Style::new()
+                    .fg(Color::Rgb(0xc0, 0x00, 0x00))
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            ],
+            depth: ColorDepth::TrueColor,
+        }
+    }
+
+    /// `high_contrast` uses only the brightest colors on black, with bold text for
+    /// anything that needs attention.
+    pub fn high_contrast() -> Self {
+        Self {
+            header: Style::new()
+                .fg(Color::Black)
+                .bg(Color::White)
+                .add_modifier(Modifier::BOLD),
+            border: Style::new().fg(Color::White),
+            text: Style::new().fg(Color::White).bg(Color::Black),
+            selected: Style::new().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+            line_number: Style::new().fg(Color::White),
+            mark: Style::new()
+                .fg(Color::LightMagenta)
+                .add_modifier(Modifier::BOLD),
+            note: Style::new().fg(Color::White).add_modifier(Modifier::ITALIC),
+            status: Style::new().fg(Color::White).add_modifier(Modifier::BOLD),
+            info: Style::new()
+                .fg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD),
+            error: Style::new()
+                .fg(Color::LightRed)
+                .add_modifier(Modifier::BOLD),
+            prompt: Style::new().fg(Color::White).add_modifier(Modifier::BOLD),
+            hint: Style::new().fg(Color::White),
+            field_key: Style::new().fg(Color::White).add_modifier(Modifier::DIM),
+            field_value: Style::new().fg(Color::LightCyan),
+            column_header: Style::new()
+                .fg(Color::LightCyan)
+                .add_modifier(Modifier::BOLD),
+            help_heading: Style::new()
+                .fg(Color::LightCyan)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            help_key: Style::new().fg(Color::LightYellow),
+            scrollbar: Style::new().fg(Color::White),
+            levels: [
+                Style::new(),
+                Style::new(),
+                Style::new(),
+                Style::new()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+                Style::new()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+                Style::new()
+                    .fg(Color::White)
+                    .bg(Color::Red)
+                    .add_modifier(Modifier::BOLD),
+            ],
+            depth: ColorDepth::TrueColor,
+        }
+    }
+
+    /// The built-in theme called `name`.
+    fn named(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" | "high_contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+
+    /// Build the theme from the `[theme]` section of the configuration file, adapted
+    /// to the colors the terminal supports.
+    pub fn from_config(config: &ThemeConfig) -> Result<Self, String> {
+        let name = config.name.as_deref().unwrap_or("dark");
+        let mut theme = Self::named(name).ok_or_else(|| {
+            format!(
+                "unknown theme '{name}', expected one of {}",
+                Self::NAMES.join(", ")
+            )
+        })?;
+
+        for (element, style) in &config.elements {
+            let target = theme
+                .element_mut(element)
+                .ok_or_else(|| format!("unknown theme element '{element}'"))?;
+            *target = patch_style(*target, style).map_err(|e| format!("{element}: {e}"))?;
+        }
+        for (name, style) in &config.levels {
+            let level = LogLevel::parse(name).ok_or_else(|| format!("unknown level '{name}'"))?;
+            let target = &mut theme.levels[level as usize];
+            *target = patch_style(*target, style).map_err(|e| format!("levels.{name}: {e}"))?;
+        }
+
+        let depth = match config.colors.as_deref() {
+            Some(colors) => ColorDepth::parse(colors)?,
+            None => None,
+        };
+        Ok(theme.adapted(depth.unwrap_or_else(ColorDepth::detect)))
+    }
+
+    /// The style of the UI element called `name`.
+    fn element_mut(&mut self, name: &str) -> Option<&mut Style> {
+        match name {
+            "header" => Some(&mut self.header),
+            "border" => Some(&mut self.border),
+            "text" => Some(&mut self.text),
+            "selected" => Some(&mut self.selected),
+            "line_number" => Some(&mut self.line_number),
+            "mark" => Some(&mut self.mark),
+            "note" => Some(&mut self.note),
+            "status" => Some(&mut self.status),
+            "info" => Some(&mut self.info),
+            "error" => Some(&mut self.error),
+            "prompt" => Some(&mut self.prompt),
+            "hint" => Some(&mut self.hint),
+            "field_key" => Some(&mut self.field_key),
+            "field_value" => Some(&mut self.field_value),
+            "column_header" => Some(&mut self.column_header),
+            "help_heading" => Some(&mut self.help_heading),
+            "help_key" => Some(&mut self.help_key),
+            "scrollbar" => Some(&mut self.scrollbar),
+            _ => None,
+        }
+    }
+
+    /// Convert every color to one the terminal with `depth` can show. Without any
+    /// color, the selection is shown reversed so it stays visible.
+    fn adapted(mut self, depth: ColorDepth) -> Self {
+        self.depth = depth;
+        for style in [
+            &mut self.header,
+            &mut self.border,
+            &mut self.text,
+            &mut self.selected,
+            &mut self.line_number,
+            &mut self.mark,
+            &mut self.note,
+            &mut self.status,
+            &mut self.info,
+            &mut self.error,
+            &mut self.prompt,
+            &mut self.hint,
+            &mut self.field_key,
+            &mut self.field_value,
+            &mut self.column_header,
+            &mut self.help_heading,
+            &mut self.help_key,
+            &mut self.scrollbar,
+        ]
+        .into_iter()
+        .chain(&mut self.levels)
+        {
+            *style = depth.adapt(*style);
+        }
+
+        if depth == ColorDepth::None {
+            self.selected = self.selected.add_modifier(Modifier::REVERSED);
+        }
+        self
+    }
+
+    /// Convert the colors of `style` to ones the terminal can show, for styles that
+    /// don't come from the theme like the colors of escape sequences in log lines.
+    pub fn adapt(&self, style: Style) -> Style {
+        self.depth.adapt(style)
+    }
+
+    /// The style patched over entries with `level`.
+    pub const fn level(&self, level: LogLevel) -> Style {
+        self.levels[level as usize]
+    }
+
+    /// The style of an entry with `level`, on top of the text style.
+    pub fn entry(&self, level: Option<LogLevel>) -> Style {
+        level.map_or(self.text, |level| self.text.patch(self.level(level)))
+    }
+}
+
+/// Apply the attributes set in `config` on top of `style`.
+fn patch_style(mut style: Style, config: &StyleConfig) -> Result<Style, String> {
+    let parse =
+        |color: &str| Color::from_str(color).map_err(|_| format!("unknown color '{color}'"));
+    if let Some(fg) = &config.fg {
+        style.fg = Some(parse(fg)?);
+    }
+    if let Some(bg) = &config.bg {
+        style.bg = Some(parse(bg)?);
+    }
+
+    for (enabled, modifier) in [
+        (config.bold, Modifier::BOLD),
+        (config.dim, Modifier::DIM),
+        (config.italic, Modifier::ITALIC),
+        (config.underline, Modifier::UNDERLINED),
+        (config.reversed, Modifier::REVERSED),
+    ] {
+        style = match enabled {
+            Some(true) => style.add_modifier(modifier),
+            Some(false) => style.remove_modifier(modifier),
+            None => style,
+        };
+    }
+    Ok(style)
+}